@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::operation::{Operation, SignalID};
+use super::sequential::level;
+use super::signal::Signal;
+
+/// Transitions of a single net, summed over every lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Transitions {
+    /// the net switched between `False` and `True`
+    pub controlled: u64,
+    /// the net switched level with an uncontrolled value on either side
+    pub uncontrolled: u64,
+}
+
+impl Transitions {
+    pub fn total(&self) -> u64 {
+        self.controlled + self.uncontrolled
+    }
+}
+
+/// Counts transitions of every net over a window of ticks.
+#[derive(Debug, Clone)]
+pub struct ActivityCounter {
+    counts: Box<[Transitions]>,
+    /// the tick the window started on
+    start: u128,
+}
+
+impl ActivityCounter {
+    pub fn new(operations: usize, start: u128) -> Self {
+        Self {
+            counts: vec![Transitions::default(); operations].into_boxed_slice(),
+            start,
+        }
+    }
+
+    /// Records one tick going from `signals` to `next`.
+    ///
+    /// Only changes of logic level count, going to or from an unknown value isn't a
+    /// transition since it can't be said whether the net really switched.
+    pub fn observe(&mut self, lanes: usize, signals: &[Signal], next: &[Signal]) {
+        for (counts, (before, after)) in
+            (self.counts.iter_mut()).zip(signals.chunks(lanes).zip(next.chunks(lanes)))
+        {
+            for (before, after) in before.iter().zip(after) {
+                match (level(*before), level(*after)) {
+                    (Some(a), Some(b)) if a != b => match (before, after) {
+                        (Signal::False | Signal::True, Signal::False | Signal::True) => {
+                            counts.controlled += 1
+                        }
+                        _ => counts.uncontrolled += 1,
+                    },
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    pub fn start(&self) -> u128 {
+        self.start
+    }
+}
+
+/// The activity of one net over the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetActivity {
+    pub signal: SignalID,
+    pub transitions: Transitions,
+    /// average transitions per input sample on each lane
+    pub activity: f64,
+    /// how many operations read the net
+    pub fanout: usize,
+    /// `activity * fanout`, a stand in for the switched capacitance of the net
+    pub power: f64,
+}
+
+/// Summary of every net and named module over a window, see `Circuit::activity`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityReport {
+    pub ticks: u128,
+    /// every net which switched, busiest first
+    pub nets: Vec<NetActivity>,
+    /// the summed power proxy of each module, going by the hierarchical names of
+    /// its nets, highest first. Nets without a name aren't counted.
+    pub modules: Vec<(String, f64)>,
+}
+
+impl ActivityReport {
+    pub fn new(
+        counter: &ActivityCounter,
+        description: &[Operation],
+        names: &HashMap<String, SignalID>,
+        ticks: u128,
+        ticks_per_input: usize,
+        lanes: usize,
+    ) -> Self {
+        let mut fanout = vec![0; description.len()];
+        for op in description.iter() {
+            for source in op.inputs() {
+                fanout[source.0] += 1;
+            }
+        }
+
+        let samples = (ticks as f64 / ticks_per_input as f64) * lanes as f64;
+        let mut nets: Vec<NetActivity> = (counter.counts.iter().enumerate())
+            .filter(|(_, counts)| counts.total() > 0)
+            .map(|(index, counts)| {
+                let activity = counts.total() as f64 / samples;
+                NetActivity {
+                    signal: SignalID(index),
+                    transitions: *counts,
+                    activity,
+                    fanout: fanout[index],
+                    power: activity * fanout[index] as f64,
+                }
+            })
+            .collect();
+        nets.sort_by(|a, b| {
+            (b.transitions.total().cmp(&a.transitions.total())).then(a.signal.cmp(&b.signal))
+        });
+
+        // a net can have several names in the same module but only counts once
+        let power: HashMap<SignalID, f64> = nets.iter().map(|n| (n.signal, n.power)).collect();
+        let mut members: BTreeMap<&str, HashSet<SignalID>> = BTreeMap::new();
+        for (name, id) in names.iter() {
+            let module = name.rsplit_once('.').map_or("", |(module, _)| module);
+            members.entry(module).or_default().insert(*id);
+        }
+        let mut modules: Vec<(String, f64)> = (members.into_iter())
+            .map(|(module, ids)| {
+                let total = ids.iter().filter_map(|id| power.get(id)).sum();
+                (module.to_string(), total)
+            })
+            .collect();
+        modules.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        Self {
+            ticks,
+            nets,
+            modules,
+        }
+    }
+
+    /// The `count` busiest nets.
+    pub fn hottest(&self, count: usize) -> &[NetActivity] {
+        &self.nets[..count.min(self.nets.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::stimulus::Stimulus;
+    use crate::back_end::circuit::Circuit;
+
+    #[test]
+    fn transition_classes() {
+        use Signal::*;
+
+        let mut counter = ActivityCounter::new(6, 0);
+        counter.observe(
+            2,
+            &[
+                False,
+                True,
+                True,
+                UncontrolledTrue,
+                False,
+                Undefined,
+                False,
+                HighImpedance,
+                True,
+                True,
+                False,
+                False,
+            ],
+            &[
+                True,
+                False,
+                True,
+                False,
+                UncontrolledTrue,
+                True,
+                Undefined,
+                True,
+                True,
+                UncontrolledTrue,
+                False,
+                False,
+            ],
+        );
+        let counts: Vec<(u64, u64)> = (counter.counts.iter())
+            .map(|t| (t.controlled, t.uncontrolled))
+            .collect();
+        assert_eq!(counts, [(2, 0), (0, 1), (0, 1), (0, 0), (0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn counts_and_ranks() {
+        // a clock, its inverse read three times, and a net stuck at false
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let not_clk = gld.rz_alloc();
+        let quiet = gld.rz_alloc();
+        let loads = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        gld.mk_stimulus(
+            clk,
+            Stimulus::Clock {
+                low: 1,
+                high: 1,
+                phase: 0,
+            },
+        )
+        .unwrap();
+        gld.mk_stimulus(quiet, Stimulus::Constant(Signal::False))
+            .unwrap();
+        gld.mk_not(not_clk, clk).unwrap();
+        for load in loads {
+            gld.mk_and(load, not_clk, quiet).unwrap();
+        }
+        let names = HashMap::from([
+            ("top.clk".to_string(), clk),
+            ("top.inv.y".to_string(), not_clk),
+            ("top.inv.out".to_string(), not_clk),
+            ("top.quiet".to_string(), quiet),
+        ]);
+
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), 2).with_names(names);
+        assert!(circuit.activity().is_none());
+        circuit.run_for(4);
+        circuit = circuit.with_activity();
+        circuit.run_for(20);
+        let report = circuit.activity().unwrap();
+
+        // inputs inject uncontrolled values on every edge so everything downstream
+        // of them switches uncontrolled
+        assert_eq!(report.ticks, 20);
+        assert_eq!(
+            report.nets[0].transitions,
+            Transitions {
+                controlled: 0,
+                uncontrolled: 10
+            }
+        );
+        assert_eq!(report.nets[0].activity, 1.0);
+        let ranked: Vec<SignalID> = report.nets.iter().map(|net| net.signal).collect();
+        assert_eq!(ranked, [clk, not_clk]);
+        assert_eq!(report.hottest(1)[0].signal, clk);
+        assert_eq!(report.nets[1].fanout, 3);
+        assert_eq!(report.nets[1].power, report.nets[1].activity * 3.0);
+        assert_eq!(report.modules[0].0, "top.inv");
+        assert_eq!(report.modules[0].1, report.nets[1].power);
+
+        circuit.reset_activity();
+        assert!(circuit.activity().unwrap().nets.is_empty());
+    }
+}
@@ -15,6 +15,568 @@ earlier in the reservation phase. At any point if an input isn't found it should
 which will always use a high impedance signal this should be easy to insert at the last index of the circuit.
 */
 
-impl From<crate::middle_end::netlist::Netlist> for crate::back_end::circuit::Circuit {
-    fn from(value: crate::middle_end::netlist::Netlist) -> Self {}
+use std::sync::Arc;
+
+use super::operation::*;
+use super::signal::Signal;
+use super::stimulus::Stimulus;
+
+/// A gate level description (gld) is the incremental form of a circuit description.
+///
+/// Signals are first reserved with `rz_alloc` which hands out a `SignalID` that
+/// may be referenced by any other operation. Each reserved ID must then be defined
+/// exactly once by one of the `mk_*` functions before `into_desc` will produce the
+/// flat description used by `Circuit::new`.
+#[derive(Debug, Default)]
+pub struct GateLevelDescription {
+    operations: Vec<Option<Operation>>,
+}
+
+impl GateLevelDescription {
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+        }
+    }
+
+    /// Reserves a new signal and returns its ID. The signal is undefined until
+    /// one of the `mk_*` functions is called on it.
+    pub fn rz_alloc(&mut self) -> SignalID {
+        self.operations.push(None);
+        SignalID(self.operations.len() - 1)
+    }
+
+    /// Returns the number of signals reserved so far.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn mk_not(&mut self, loc: SignalID, a: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.define(loc, Operation::Not(a))
+    }
+
+    pub fn mk_and(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::And(a, b))
+    }
+
+    pub fn mk_nand(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Nand(a, b))
+    }
+
+    pub fn mk_or(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Or(a, b))
+    }
+
+    pub fn mk_nor(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Nor(a, b))
+    }
+
+    pub fn mk_xor(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Xor(a, b))
+    }
+
+    pub fn mk_xnor(&mut self, loc: SignalID, a: SignalID, b: SignalID) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Xnor(a, b))
+    }
+
+    pub fn mk_and_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::AndN(inputs.into()))
+    }
+
+    pub fn mk_nand_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::NandN(inputs.into()))
+    }
+
+    pub fn mk_or_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::OrN(inputs.into()))
+    }
+
+    pub fn mk_nor_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::NorN(inputs.into()))
+    }
+
+    pub fn mk_xor_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::XorN(inputs.into()))
+    }
+
+    pub fn mk_xnor_n(&mut self, loc: SignalID, inputs: &[SignalID]) -> Result<(), BuilderError> {
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::XnorN(inputs.into()))
+    }
+
+    /// Defines a multiplexer passing `a` while `select` is false and `b` while it is true.
+    pub fn mk_mux(
+        &mut self,
+        loc: SignalID,
+        select: SignalID,
+        a: SignalID,
+        b: SignalID,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(select)?;
+        self.check_allocated(a)?;
+        self.check_allocated(b)?;
+        self.define(loc, Operation::Mux(select, a, b))
+    }
+
+    /// Defines a lookup table, see `Signal::lookup` for the layout of `table`.
+    pub fn mk_lut(
+        &mut self,
+        loc: SignalID,
+        inputs: &[SignalID],
+        table: u64,
+    ) -> Result<(), BuilderError> {
+        if inputs.len() > 6 {
+            return Err(BuilderError::LutTooWide(loc));
+        }
+        for input in inputs {
+            self.check_allocated(*input)?;
+        }
+        self.define(loc, Operation::Lut(inputs.into(), table))
+    }
+
+    /// Defines a rising edge triggered flip-flop with optional asynchronous reset
+    /// and set. An extra signal is reserved to hold the clock from the previous tick.
+    pub fn mk_dff(
+        &mut self,
+        loc: SignalID,
+        d: SignalID,
+        clk: SignalID,
+        reset: Option<SignalID>,
+        set: Option<SignalID>,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(d)?;
+        self.check_allocated(clk)?;
+        for control in [reset, set].into_iter().flatten() {
+            self.check_allocated(control)?;
+        }
+        self.check_undefined(loc)?;
+
+        let last_clk = self.last_clk(clk)?;
+        self.define(
+            loc,
+            Operation::Dff(FlipFlop {
+                d,
+                clk,
+                last_clk,
+                reset,
+                set,
+            }),
+        )
+    }
+
+    /// Defines a RAM at `loc` with `2^address.len()` words as wide as `data_in`.
+    /// Each ID in `data_out` must be reserved beforehand and is defined as one bit of
+    /// the data output. Writes happen on the rising edge of `clk`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mk_ram(
+        &mut self,
+        loc: SignalID,
+        data_out: &[SignalID],
+        address: &[SignalID],
+        data_in: &[SignalID],
+        write_enable: SignalID,
+        clk: SignalID,
+        read: ReadMode,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(write_enable)?;
+        self.check_allocated(clk)?;
+        for id in data_in {
+            self.check_allocated(*id)?;
+        }
+        if data_out.len() != data_in.len() {
+            return Err(BuilderError::MemoryWidth(loc));
+        }
+        self.check_memory(loc, data_out, address)?;
+
+        let last_clk = self.last_clk(clk)?;
+        self.mk_memory(
+            loc,
+            data_out,
+            MemoryBlock {
+                width: data_in.len(),
+                address: address.into(),
+                data_in: data_in.into(),
+                write_enable: Some(write_enable),
+                clock: Some((clk, last_clk)),
+                read,
+            },
+        )
+    }
+
+    /// Defines a ROM at `loc` with `2^address.len()` words as wide as `data_out`, its
+    /// contents are set with `Circuit::load_memory`. Reads are synchronous when a
+    /// clock is given.
+    pub fn mk_rom(
+        &mut self,
+        loc: SignalID,
+        data_out: &[SignalID],
+        address: &[SignalID],
+        clk: Option<SignalID>,
+    ) -> Result<(), BuilderError> {
+        self.check_memory(loc, data_out, address)?;
+        let (clock, read) = match clk {
+            Some(clk) => {
+                self.check_allocated(clk)?;
+                (Some((clk, self.last_clk(clk)?)), ReadMode::Synchronous)
+            }
+            None => (None, ReadMode::Asynchronous),
+        };
+        self.mk_memory(
+            loc,
+            data_out,
+            MemoryBlock {
+                width: data_out.len(),
+                address: address.into(),
+                data_in: Box::new([]),
+                write_enable: None,
+                clock,
+                read,
+            },
+        )
+    }
+
+    pub fn mk_dlatch(
+        &mut self,
+        loc: SignalID,
+        d: SignalID,
+        enable: SignalID,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(d)?;
+        self.check_allocated(enable)?;
+        self.define(loc, Operation::DLatch(d, enable))
+    }
+
+    pub fn mk_tribuf(
+        &mut self,
+        loc: SignalID,
+        data: SignalID,
+        enable: SignalID,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(data)?;
+        self.check_allocated(enable)?;
+        self.define(loc, Operation::TriBuf(data, enable))
+    }
+
+    /// Defines a net driven by every signal in `drivers`, typically the outputs
+    /// of tri-state buffers.
+    pub fn mk_resolve(&mut self, loc: SignalID, drivers: &[SignalID]) -> Result<(), BuilderError> {
+        for driver in drivers {
+            self.check_allocated(*driver)?;
+        }
+        self.define(loc, Operation::Resolve(drivers.into()))
+    }
+
+    pub fn mk_input(
+        &mut self,
+        loc: SignalID,
+        expr: Arc<dyn Fn(usize, u128) -> Signal + Sync + Send>,
+    ) -> Result<(), BuilderError> {
+        self.define(loc, Operation::Input(InputHandler::new(expr)))
+    }
+
+    pub fn mk_output(
+        &mut self,
+        loc: SignalID,
+        a: SignalID,
+        expr: Arc<dyn Fn(usize, u128, Signal) + Sync + Send>,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.define(loc, Operation::Output(a, OutputHandler::new(expr)))
+    }
+
+    /// Defines an input driven by a declarative stimulus rather than a closure.
+    pub fn mk_stimulus(&mut self, loc: SignalID, stimulus: Stimulus) -> Result<(), BuilderError> {
+        self.define(loc, Operation::Stimulus(Box::new(stimulus)))
+    }
+
+    pub fn mk_lane_input(
+        &mut self,
+        loc: SignalID,
+        expr: Arc<dyn Fn(usize, u128, usize) -> Signal + Sync + Send>,
+    ) -> Result<(), BuilderError> {
+        self.define(loc, Operation::LaneInput(LaneInputHandler::new(expr)))
+    }
+
+    pub fn mk_lane_output(
+        &mut self,
+        loc: SignalID,
+        a: SignalID,
+        expr: Arc<dyn Fn(usize, u128, usize, Signal) + Sync + Send>,
+    ) -> Result<(), BuilderError> {
+        self.check_allocated(a)?;
+        self.define(loc, Operation::LaneOutput(a, LaneOutputHandler::new(expr)))
+    }
+
+    /// Gives an already defined operation a delay longer than a single tick,
+    /// replacing any delay it had before.
+    pub fn set_delay(&mut self, loc: SignalID, delay: Delay) -> Result<(), BuilderError> {
+        self.check_allocated(loc)?;
+        let op = match self.operations[loc.0].take() {
+            Some(Operation::Delayed(op, _)) => op,
+            Some(op) => Box::new(op),
+            None => return Err(BuilderError::Undefined(vec![loc])),
+        };
+        self.define(loc, Operation::Delayed(op, delay))
+    }
+
+    /// Consumes the builder producing the description expected by `Circuit::new`.
+    ///
+    /// Fails listing every ID which was reserved but never given an operation.
+    pub fn into_desc(self) -> Result<Box<[Operation]>, BuilderError> {
+        let undefined: Vec<SignalID> = self
+            .operations
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| op.is_none())
+            .map(|(idx, _)| SignalID(idx))
+            .collect();
+
+        if !undefined.is_empty() {
+            return Err(BuilderError::Undefined(undefined));
+        }
+
+        Ok(self.operations.into_iter().flatten().collect())
+    }
+
+    /// Checks everything `mk_memory` could fail on before the clock buffer is
+    /// defined, so a rejected memory leaves nothing behind.
+    fn check_memory(
+        &self,
+        loc: SignalID,
+        data_out: &[SignalID],
+        address: &[SignalID],
+    ) -> Result<(), BuilderError> {
+        if address.len() > MemoryBlock::MAX_ADDRESS_BITS {
+            return Err(BuilderError::AddressTooWide(loc));
+        }
+        for id in address {
+            self.check_allocated(*id)?;
+        }
+        let mut defined: Vec<SignalID> = Vec::with_capacity(data_out.len() + 1);
+        for id in std::iter::once(&loc).chain(data_out) {
+            match self.operations.get(id.0) {
+                None => return Err(BuilderError::NotAllocated(*id)),
+                Some(Some(_)) => return Err(BuilderError::DoubleDefinition(*id)),
+                Some(None) if defined.contains(id) => {
+                    return Err(BuilderError::DoubleDefinition(*id))
+                }
+                Some(None) => defined.push(*id),
+            }
+        }
+        Ok(())
+    }
+
+    fn mk_memory(
+        &mut self,
+        loc: SignalID,
+        data_out: &[SignalID],
+        block: MemoryBlock,
+    ) -> Result<(), BuilderError> {
+        self.define(loc, Operation::Memory(Box::new(block)))?;
+        for (bit, id) in data_out.iter().enumerate() {
+            self.define(*id, Operation::MemRead(loc, bit))?;
+        }
+        Ok(())
+    }
+
+    /// Reserves a buffer holding the value `clk` had on the previous tick, which
+    /// clocked operations compare against to find edges.
+    fn last_clk(&mut self, clk: SignalID) -> Result<SignalID, BuilderError> {
+        let last_clk = self.rz_alloc();
+        self.define(last_clk, Operation::And(clk, clk))?;
+        Ok(last_clk)
+    }
+
+    /// Checks `loc` can still be defined, before anything else is reserved for it.
+    fn check_undefined(&self, loc: SignalID) -> Result<(), BuilderError> {
+        match self.operations.get(loc.0) {
+            Some(None) => Ok(()),
+            Some(Some(_)) => Err(BuilderError::DoubleDefinition(loc)),
+            None => Err(BuilderError::NotAllocated(loc)),
+        }
+    }
+
+    fn check_allocated(&self, id: SignalID) -> Result<(), BuilderError> {
+        match id.0 < self.operations.len() {
+            true => Ok(()),
+            false => Err(BuilderError::NotAllocated(id)),
+        }
+    }
+
+    fn define(&mut self, loc: SignalID, op: Operation) -> Result<(), BuilderError> {
+        match self.operations.get_mut(loc.0) {
+            Some(slot @ None) => {
+                *slot = Some(op);
+                Ok(())
+            }
+            Some(Some(_)) => Err(BuilderError::DoubleDefinition(loc)),
+            None => Err(BuilderError::NotAllocated(loc)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    /// The ID was never handed out by `rz_alloc`.
+    NotAllocated(SignalID),
+    /// The ID already had an operation assigned to it.
+    DoubleDefinition(SignalID),
+    /// These IDs were reserved but never defined.
+    Undefined(Vec<SignalID>),
+    /// A lookup table was given more than 6 inputs.
+    LutTooWide(SignalID),
+    /// The data output of a memory isn't as wide as its data input.
+    MemoryWidth(SignalID),
+    /// A memory was given more than `MemoryBlock::MAX_ADDRESS_BITS` address bits.
+    AddressTooWide(SignalID),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::Circuit;
+
+    #[test]
+    fn build_and_sim_inverter() {
+        let mut gld = GateLevelDescription::new();
+        let input = gld.rz_alloc();
+        let inverted = gld.rz_alloc();
+
+        gld.mk_input(input, Arc::new(|_index, _tick| Signal::True))
+            .unwrap();
+        gld.mk_not(inverted, input).unwrap();
+
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), 4);
+        circuit.run_for(4);
+
+        let (description, signals) = circuit.inspect();
+        assert_eq!(description.len(), 2);
+        assert_eq!(signals[inverted.0], Signal::False);
+    }
+
+    #[test]
+    fn double_definition_is_rejected() {
+        let mut gld = GateLevelDescription::new();
+        let a = gld.rz_alloc();
+        let b = gld.rz_alloc();
+
+        gld.mk_not(b, a).unwrap();
+        assert_eq!(gld.mk_not(b, a), Err(BuilderError::DoubleDefinition(b)));
+    }
+
+    #[test]
+    fn unallocated_ids_are_rejected() {
+        let mut gld = GateLevelDescription::new();
+        let a = gld.rz_alloc();
+
+        assert_eq!(
+            gld.mk_and(a, a, SignalID(7)),
+            Err(BuilderError::NotAllocated(SignalID(7)))
+        );
+        assert_eq!(
+            gld.mk_not(SignalID(3), a),
+            Err(BuilderError::NotAllocated(SignalID(3)))
+        );
+    }
+
+    #[test]
+    fn rejected_memories_leave_nothing_behind() {
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let loc = gld.rz_alloc();
+        let out = gld.rz_alloc();
+        gld.mk_input(clk, Arc::new(|_index, _tick| Signal::True))
+            .unwrap();
+        gld.mk_not(out, clk).unwrap();
+
+        let address = vec![clk; MemoryBlock::MAX_ADDRESS_BITS + 1];
+        assert_eq!(
+            gld.mk_rom(loc, &[], &address, None),
+            Err(BuilderError::AddressTooWide(loc))
+        );
+        assert_eq!(
+            gld.mk_ram(loc, &[out], &[clk], &[clk], clk, clk, ReadMode::Synchronous),
+            Err(BuilderError::DoubleDefinition(out))
+        );
+        assert_eq!(
+            gld.mk_rom(loc, &[loc], &[clk], Some(clk)),
+            Err(BuilderError::DoubleDefinition(loc))
+        );
+        // no clock buffer was reserved for the failed memories
+        assert_eq!(gld.operations.len(), 3);
+        assert_eq!(
+            gld.into_desc().unwrap_err(),
+            BuilderError::Undefined(vec![loc])
+        );
+    }
+
+    #[test]
+    fn rejected_flip_flops_leave_nothing_behind() {
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let q = gld.rz_alloc();
+        gld.mk_input(clk, Arc::new(|_index, _tick| Signal::True))
+            .unwrap();
+        gld.mk_dff(q, clk, clk, None, None).unwrap();
+
+        assert_eq!(
+            gld.mk_dff(q, clk, clk, None, None),
+            Err(BuilderError::DoubleDefinition(q))
+        );
+        assert_eq!(
+            gld.mk_dff(clk, q, clk, None, None),
+            Err(BuilderError::DoubleDefinition(clk))
+        );
+        // only the first flip-flop's clock buffer was reserved
+        assert_eq!(gld.operations.len(), 3);
+        assert!(gld.into_desc().is_ok());
+    }
+
+    #[test]
+    fn undefined_ids_are_reported() {
+        let mut gld = GateLevelDescription::new();
+        let a = gld.rz_alloc();
+        let b = gld.rz_alloc();
+        let c = gld.rz_alloc();
+
+        gld.mk_not(b, a).unwrap();
+        assert_eq!(
+            gld.into_desc().unwrap_err(),
+            BuilderError::Undefined(vec![a, c])
+        );
+    }
 }
@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+
+use super::operation::{Delay, DelayKind};
+use super::signal::Signal;
+
+/// Pending output transitions for one lane of a delayed operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DelayLine {
+    /// values waiting to appear on the output and the tick they are due on, oldest first
+    pub(super) pending: VecDeque<(u128, Signal)>,
+    /// the last value handed to the line, `None` means the current output
+    pub(super) scheduled: Option<Signal>,
+}
+
+impl DelayLine {
+    /// Drops any pending transitions, used when the state of the circuit is
+    /// modified outside of `tick`.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.scheduled = None;
+    }
+
+    /// Whether every transition handed to the line has reached the output.
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Feeds the value the operation computed on `tick` into the line and returns
+    /// the value the output should take on the next tick.
+    ///
+    /// With a delay of one tick the computed value is always returned, so delayed
+    /// operations behave just like the rest of the circuit in that case.
+    pub fn step(&mut self, tick: u128, delay: Delay, computed: Signal, current: Signal) -> Signal {
+        let scheduled = self.scheduled.unwrap_or(current);
+        if computed != scheduled {
+            match delay.kind {
+                DelayKind::Transport => (),
+                // anything still in flight is a pulse shorter than the delay
+                DelayKind::Inertial => self.pending.clear(),
+            }
+            if delay.kind == DelayKind::Transport || computed != current {
+                self.pending
+                    .push_back((tick + delay.ticks as u128, computed));
+            }
+            self.scheduled = Some(computed);
+        }
+
+        let mut next = current;
+        while let Some(&(due, value)) = self.pending.front() {
+            if due > tick + 1 {
+                break;
+            }
+            next = value;
+            self.pending.pop_front();
+        }
+        next
+    }
+}
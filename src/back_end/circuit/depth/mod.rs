@@ -0,0 +1,316 @@
+use std::fmt::Display;
+
+use super::graph;
+use super::is_output;
+use super::operation::{Operation, ReadMode, SignalID};
+
+/// The longest path found from one input to one output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingPath {
+    pub input: SignalID,
+    pub output: SignalID,
+    /// ticks after an input is sampled before the output can have settled, counting
+    /// the input and the output themselves
+    pub ticks: usize,
+    /// the operations along the path starting with the input, a loop on the path is
+    /// listed by the member the path enters it at followed by the one it leaves by
+    pub path: Vec<SignalID>,
+    /// Whether the path runs through a combinational loop. Loops are counted as a
+    /// single trip around every member, which is enough for a latch to settle but
+    /// no bound at all for a ring which oscillates.
+    pub through_loop: bool,
+}
+
+impl Display for TimingPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ticks:", self.ticks)?;
+        for id in self.path.iter() {
+            write!(f, " {}", id.0)?;
+        }
+        if self.through_loop {
+            write!(f, " (through a loop)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The logic depth of a circuit, see `logic_depth`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepthReport {
+    /// the longest path between every input and output connected by one, deepest first
+    pub paths: Vec<TimingPath>,
+}
+
+impl DepthReport {
+    /// The deepest path of the circuit, `None` when no output depends on an input.
+    pub fn critical(&self) -> Option<&TimingPath> {
+        self.paths.first()
+    }
+
+    /// Ticks per input which let the critical path settle with `margin` ticks to
+    /// spare.
+    pub fn ticks_per_input(&self, margin: usize) -> usize {
+        let depth = self.critical().map(|path| path.ticks).unwrap_or(0);
+        (depth + margin).max(1)
+    }
+}
+
+/// Finds the longest path from every input to every output of a description
+/// without simulating it, in ticks of the full sweep kernel.
+///
+/// Flip-flops and synchronous memories are only followed from their clocks and
+/// asynchronous controls, since a value on their data inputs doesn't appear until a
+/// later clock edge. Delayed operations count their full delay.
+pub fn logic_depth(description: &[Operation]) -> DepthReport {
+    let reads = |index: usize| timing_inputs(&description[index]);
+    let components = graph::components_reading(description, reads);
+
+    let mut paths = Vec::new();
+    for (input, op) in description.iter().enumerate() {
+        if !is_input(op) {
+            continue;
+        }
+        let arrivals = arrivals(description, &components, input);
+        for (output, op) in description.iter().enumerate() {
+            let Some(ticks) = arrivals.ticks[output] else {
+                continue;
+            };
+            if !is_output(op) {
+                continue;
+            }
+            let mut path = vec![SignalID(output)];
+            while let Some(previous) = arrivals.previous[path.last().unwrap().0] {
+                path.push(SignalID(previous));
+            }
+            path.reverse();
+            paths.push(TimingPath {
+                input: SignalID(input),
+                output: SignalID(output),
+                ticks,
+                path,
+                through_loop: arrivals.through_loop[output],
+            });
+        }
+    }
+    paths.sort_by(|a, b| {
+        (b.ticks.cmp(&a.ticks)).then((a.input, a.output).cmp(&(b.input, b.output)))
+    });
+    DepthReport { paths }
+}
+
+/// The latest tick each operation can change on after a single input changes.
+struct Arrivals {
+    ticks: Vec<Option<usize>>,
+    /// the operation each one was reached from on the longest path
+    previous: Vec<Option<usize>>,
+    through_loop: Vec<bool>,
+}
+
+fn arrivals(description: &[Operation], components: &[Vec<usize>], input: usize) -> Arrivals {
+    let mut arrivals = Arrivals {
+        ticks: vec![None; description.len()],
+        previous: vec![None; description.len()],
+        through_loop: vec![false; description.len()],
+    };
+    arrivals.ticks[input] = Some(delay(&description[input]));
+
+    // components are in data flow order so every source outside of a component is
+    // final by the time it is reached
+    for component in components.iter() {
+        // the latest way into the component as (ticks, entered at, reached from)
+        let mut latest: Option<(usize, usize, usize)> = None;
+        for member in component.iter() {
+            for source in timing_inputs(&description[*member]) {
+                if component.contains(&source.0) {
+                    continue;
+                }
+                if let Some(ticks) = arrivals.ticks[source.0] {
+                    let ticks = ticks + delay(&description[*member]);
+                    if latest.map_or(true, |(latest, _, _)| ticks > latest) {
+                        latest = Some((ticks, *member, source.0));
+                    }
+                }
+            }
+        }
+        let Some((ticks, entry, source)) = latest else {
+            continue;
+        };
+
+        let through_loop = arrivals.through_loop[source] || is_loop(description, component);
+        // once around the loop from where it was entered
+        let ticks = ticks
+            + (component.iter())
+                .filter(|member| **member != entry)
+                .map(|member| delay(&description[*member]))
+                .sum::<usize>();
+        for member in component.iter() {
+            arrivals.ticks[*member] = Some(ticks);
+            arrivals.through_loop[*member] = through_loop;
+            arrivals.previous[*member] = match *member == entry {
+                true => Some(source),
+                false => Some(entry),
+            };
+        }
+    }
+    arrivals
+}
+
+/// The inputs along which a change propagates through an operation within a
+/// sample, leaving out the data inputs of edge triggered operations and the
+/// buffered clocks their edges are found with.
+fn timing_inputs(op: &Operation) -> Vec<SignalID> {
+    match op {
+        Operation::Dff(ff) => [Some(ff.clk), ff.reset, ff.set]
+            .into_iter()
+            .flatten()
+            .collect(),
+        Operation::Memory(block) if block.read == ReadMode::Synchronous => {
+            block.clock.iter().map(|(clk, _)| *clk).collect()
+        }
+        Operation::Memory(block) => {
+            let last_clk = block.clock.map(|(_, last)| last);
+            (op.inputs().into_iter())
+                .filter(|id| Some(*id) != last_clk)
+                .collect()
+        }
+        Operation::Delayed(op, _) => timing_inputs(op),
+        _ => op.inputs(),
+    }
+}
+
+/// Whether a component holds a feedback path under `timing_inputs`.
+fn is_loop(description: &[Operation], component: &[usize]) -> bool {
+    match component {
+        [index] => timing_inputs(&description[*index]).contains(&SignalID(*index)),
+        _ => true,
+    }
+}
+
+fn is_input(op: &Operation) -> bool {
+    match op {
+        Operation::Input(_) | Operation::LaneInput(_) | Operation::Stimulus(_) => true,
+        Operation::Delayed(op, _) => is_input(op),
+        _ => false,
+    }
+}
+
+fn delay(op: &Operation) -> usize {
+    match op {
+        Operation::Delayed(_, delay) => delay.ticks,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::operation::{Delay, DelayKind};
+    use crate::back_end::circuit::sequential::level;
+    use crate::back_end::circuit::signal::Signal;
+    use crate::back_end::circuit::stimulus::Stimulus;
+    use crate::back_end::circuit::Circuit;
+    use std::sync::Arc;
+
+    #[test]
+    fn finds_the_critical_path() {
+        let mut gld = GateLevelDescription::new();
+        let [a, b, c] = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        let [n1, n2, g, out] = [
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+        ];
+        let [q, q_out] = [gld.rz_alloc(), gld.rz_alloc()];
+        let [l0, l1, l_out] = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        let clock = Stimulus::Clock {
+            low: 1,
+            high: 1,
+            phase: 0,
+        };
+        gld.mk_stimulus(a, clock).unwrap();
+        gld.mk_stimulus(b, Stimulus::Constant(Signal::True))
+            .unwrap();
+        gld.mk_stimulus(c, Stimulus::Constant(Signal::True))
+            .unwrap();
+
+        // a -> n1 -> n2 (3 ticks) -> g -> out, with b joining at g
+        gld.mk_not(n1, a).unwrap();
+        gld.mk_not(n2, n1).unwrap();
+        gld.mk_and(g, n2, b).unwrap();
+        gld.mk_output(out, g, Arc::new(|_, _, _| ())).unwrap();
+        let delay = Delay {
+            ticks: 3,
+            kind: DelayKind::Transport,
+        };
+        gld.set_delay(n2, delay).unwrap();
+        // the data input of a flip-flop isn't followed, only its clock
+        gld.mk_dff(q, n1, a, None, None).unwrap();
+        gld.mk_output(q_out, q, Arc::new(|_, _, _| ())).unwrap();
+        // a latch from c
+        gld.mk_nand(l0, c, l1).unwrap();
+        gld.mk_nand(l1, c, l0).unwrap();
+        gld.mk_output(l_out, l1, Arc::new(|_, _, _| ())).unwrap();
+
+        let report = logic_depth(&gld.into_desc().unwrap());
+        let summary: Vec<(SignalID, SignalID, usize, bool)> = (report.paths.iter())
+            .map(|path| (path.input, path.output, path.ticks, path.through_loop))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (a, out, 7, false),
+                (c, l_out, 4, true),
+                (a, q_out, 3, false),
+                (b, out, 3, false),
+            ]
+        );
+        let critical = report.critical().unwrap();
+        assert_eq!(critical.path, [a, n1, n2, g, out]);
+        assert_eq!(
+            critical.to_string(),
+            format!("7 ticks: {} {} {} {} {}", a.0, n1.0, n2.0, g.0, out.0)
+        );
+        assert_eq!(report.paths[1].path, [c, l0, l1, l_out]);
+        assert_eq!(report.ticks_per_input(2), 9);
+        assert_eq!(DepthReport::default().ticks_per_input(0), 1);
+    }
+
+    #[test]
+    fn outputs_settle_within_the_chosen_tpi() {
+        let mut gld = GateLevelDescription::new();
+        let input = gld.rz_alloc();
+        let chain: Vec<SignalID> = (0..5).map(|_| gld.rz_alloc()).collect();
+        let out = gld.rz_alloc();
+        let clock = Stimulus::Clock {
+            low: 1,
+            high: 1,
+            phase: 0,
+        };
+        gld.mk_stimulus(input, clock).unwrap();
+        let mut previous = input;
+        for id in chain.iter() {
+            gld.mk_not(*id, previous).unwrap();
+            previous = *id;
+        }
+        gld.mk_output(out, previous, Arc::new(|_, _, _| ()))
+            .unwrap();
+
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), 1).with_auto_tpi(0);
+        assert_eq!(circuit.ticks_per_input, 7);
+        let clock = Stimulus::Clock {
+            low: 1,
+            high: 1,
+            phase: 0,
+        };
+        for sample in 0..6 {
+            for _ in 0..circuit.ticks_per_input {
+                circuit.tick();
+            }
+            // an odd chain of inverters
+            let expected = level(clock.value(sample)).map(|value| !value);
+            assert_eq!(level(circuit.signals[out.0]), expected);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use rayon::prelude::*;
+
+use super::operation::Operation;
+use super::signal::Signal;
+use super::TickContext;
+
+/// Book keeping for the event driven kernel.
+///
+/// Rather than sweeping the whole description every tick only operations
+/// which read a signal that changed on the previous tick are evaluated. Every
+/// other operation would produce the value it already holds so it is skipped.
+#[derive(Debug)]
+pub struct EventState {
+    /// for each signal the indexes of the operations which read it
+    fanout: Box<[Box<[usize]>]>,
+    /// inputs are re-sampled every TPI ticks regardless of activity
+    inputs: Box<[usize]>,
+    /// outputs are evaluated every tick so that their handlers see every tick
+    outputs: Box<[usize]>,
+    /// signals which changed value on the previous tick
+    changed: Vec<usize>,
+    /// set when the whole description must be evaluated on the next tick
+    stale: bool,
+    /// scratch space used to deduplicate the set of operations to evaluate
+    marked: Box<[bool]>,
+}
+
+impl EventState {
+    pub fn new(description: &[Operation]) -> Self {
+        let mut fanout = vec![Vec::new(); description.len()];
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+
+        for (index, op) in description.iter().enumerate() {
+            match op {
+                Operation::Input(_) | Operation::LaneInput(_) | Operation::Stimulus(_) => {
+                    inputs.push(index)
+                }
+                Operation::Output(_, _) | Operation::LaneOutput(_, _) => outputs.push(index),
+                _ => (),
+            }
+            for source in op.inputs() {
+                fanout[source.0].push(index);
+            }
+        }
+
+        Self {
+            fanout: fanout.into_iter().map(Vec::into_boxed_slice).collect(),
+            inputs: inputs.into_boxed_slice(),
+            outputs: outputs.into_boxed_slice(),
+            changed: Vec::new(),
+            stale: true,
+            marked: vec![false; description.len()].into_boxed_slice(),
+        }
+    }
+
+    /// Forces every operation to be evaluated on the next tick.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Records signals which were written after the last tick computed them, so
+    /// their readers are evaluated on the next tick.
+    pub fn touch(&mut self, indexes: impl Iterator<Item = usize>) {
+        self.changed.extend(indexes);
+    }
+
+    /// Writes the next state of the circuit into `swap`.
+    ///
+    /// `swap` is expected to hold the state from the previous tick, which only
+    /// differs from `signals` at the indexes recorded in `changed`.
+    pub fn tick(&mut self, context: &TickContext, swap: &mut [Signal]) {
+        let signals = context.signals;
+        let dirty = match self.stale {
+            true => (0..context.description.len()).collect(),
+            false => self.dirty(context.tick_counter % context.ticks_per_input as u128 == 0),
+        };
+
+        let updates: Vec<(usize, Signal)> = dirty
+            .par_iter()
+            .filter_map(|&index| {
+                let next = context.evaluate(index, 0);
+                match next != signals[index] {
+                    true => Some((index, next)),
+                    false => None,
+                }
+            })
+            .collect();
+
+        // bring swap up to date with the current state then apply this tick's changes
+        match self.stale {
+            true => swap.copy_from_slice(signals),
+            false => {
+                for &index in &self.changed {
+                    swap[index] = signals[index];
+                }
+            }
+        }
+        for &(index, signal) in &updates {
+            swap[index] = signal;
+        }
+
+        self.changed = updates.into_iter().map(|(index, _)| index).collect();
+        self.stale = false;
+    }
+
+    /// Collects the operations which need evaluating this tick.
+    fn dirty(&mut self, sample_inputs: bool) -> Vec<usize> {
+        let mut dirty = Vec::new();
+        let mut mark = |index: usize, dirty: &mut Vec<usize>| {
+            if !self.marked[index] {
+                self.marked[index] = true;
+                dirty.push(index);
+            }
+        };
+
+        for &index in self.changed.iter() {
+            for &reader in self.fanout[index].iter() {
+                mark(reader, &mut dirty);
+            }
+        }
+        for &index in self.outputs.iter() {
+            mark(index, &mut dirty);
+        }
+        if sample_inputs {
+            for &index in self.inputs.iter() {
+                mark(index, &mut dirty);
+            }
+        }
+
+        for &index in &dirty {
+            self.marked[index] = false;
+        }
+        dirty
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt::Display;
+
+use rayon::prelude::*;
+
+use super::operation::{Operation, SignalID};
+use super::sequential::level;
+use super::signal::Signal;
+use super::{is_output, Circuit};
+
+/// A net permanently tied to one value, the usual model of a manufacturing defect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fault {
+    pub signal: SignalID,
+    /// `true` for stuck-at-1, `false` for stuck-at-0
+    pub stuck_at: bool,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "signal {} stuck-at-{}",
+            self.signal.0, self.stuck_at as u8
+        )
+    }
+}
+
+/// The outcome of grading a stimulus against every stuck-at fault of a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultReport {
+    /// faults which changed an output, with the input sample they were first seen on
+    pub detected: Vec<(Fault, u128)>,
+    pub undetected: Vec<Fault>,
+}
+
+impl FaultReport {
+    /// The percentage of faults which were detected, 100 for a circuit without any.
+    pub fn coverage(&self) -> f64 {
+        let total = self.detected.len() + self.undetected.len();
+        match total {
+            0 => 100.0,
+            _ => self.detected.len() as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+/// Simulates the good circuit and one copy per fault for `samples` input samples,
+/// comparing every output at the end of each sample like a tester strobing them.
+///
+/// A fault counts as detected once an output of the faulty copy holds the opposite
+/// logic level to the good circuit, unknown values on either side are never a
+/// difference. Output handlers aren't called while grading.
+///
+/// Every copy runs on the kernel and from the initial state policy of `circuit`
+/// with its forces in place, a fault on a forced signal replaces the force.
+pub fn simulate(circuit: &Circuit, samples: u128) -> FaultReport {
+    let description: Box<[Operation]> = circuit.description.iter().map(silence).collect();
+    let outputs: Vec<usize> = (circuit.description.iter().enumerate())
+        .filter(|(_, op)| is_output(op))
+        .map(|(index, _)| index)
+        .collect();
+
+    let fresh = || {
+        let mut copy = Circuit::new(description.clone(), circuit.ticks_per_input)
+            .with_init(circuit.init)
+            .with_kernel(circuit.kernel());
+        for (id, value) in circuit.forced() {
+            copy.force(id, value)
+                .expect("forces are on signals of the circuit");
+        }
+        for memory in circuit.memories.iter() {
+            copy.load_memory(SignalID(memory.op), memory.lane(0))
+                .expect("memories are laid out identically");
+        }
+        copy
+    };
+    let run = |mut copy: Circuit, good: Option<&[Vec<Signal>]>| {
+        let mut strobes = Vec::new();
+        for sample in 0..samples {
+            copy.run_for(circuit.ticks_per_input as u128);
+            let signals = copy.inspect().1;
+            let strobe: Vec<Signal> = outputs.iter().map(|index| signals[*index]).collect();
+            if let Some(good) = good {
+                let differs = (strobe.iter().zip(good[sample as usize].iter()))
+                    .any(|(a, b)| matches!((level(*a), level(*b)), (Some(a), Some(b)) if a != b));
+                if differs {
+                    return Err(sample);
+                }
+            }
+            strobes.push(strobe);
+        }
+        Ok(strobes)
+    };
+
+    let good = run(fresh(), None).expect("the good circuit is never compared");
+    let faults: Vec<Fault> = (circuit.description.iter().enumerate())
+        .filter(|(_, op)| !matches!(op, Operation::Memory(_)))
+        .flat_map(|(index, _)| {
+            [false, true].map(|stuck_at| Fault {
+                signal: SignalID(index),
+                stuck_at,
+            })
+        })
+        .collect();
+
+    let results: Vec<(Fault, Result<_, u128>)> = faults
+        .into_par_iter()
+        .map(|fault| {
+            let mut copy = fresh();
+            let value = match fault.stuck_at {
+                true => Signal::True,
+                false => Signal::False,
+            };
+            copy.force(fault.signal, value)
+                .expect("every fault is on a signal");
+            (fault, run(copy, Some(&good)).map(|_| ()))
+        })
+        .collect();
+
+    let mut report = FaultReport {
+        detected: Vec::new(),
+        undetected: Vec::new(),
+    };
+    for (fault, result) in results {
+        match result {
+            Ok(()) => report.undetected.push(fault),
+            Err(sample) => report.detected.push((fault, sample)),
+        }
+    }
+    report
+}
+
+/// Replaces outputs with plain buffers so user handlers don't see the faulty copies.
+fn silence(op: &Operation) -> Operation {
+    match op {
+        Operation::Output(a, _) | Operation::LaneOutput(a, _) => Operation::And(*a, *a),
+        Operation::Delayed(op, delay) => Operation::Delayed(Box::new(silence(op)), *delay),
+        _ => op.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::Kernel;
+    use std::sync::Arc;
+
+    fn and_gate(patterns: u128) -> (Circuit, [SignalID; 4]) {
+        let mut gld = GateLevelDescription::new();
+        let ids = [
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+            gld.rz_alloc(),
+        ];
+        let [a, b, y, out] = ids;
+        for (bit, id) in [(0, a), (1, b)] {
+            gld.mk_input(
+                id,
+                Arc::new(move |_, sample| match (sample % patterns) >> bit & 1 {
+                    0 => Signal::True,
+                    _ => Signal::False,
+                }),
+            )
+            .unwrap();
+        }
+        gld.mk_and(y, a, b).unwrap();
+        gld.mk_output(out, y, Arc::new(|_, _, _| panic!("outputs are silenced")))
+            .unwrap();
+        (Circuit::new(gld.into_desc().unwrap(), 4), ids)
+    }
+
+    #[test]
+    fn stuck_at_coverage() {
+        // every input combination finds every fault
+        let (circuit, _) = and_gate(4);
+        let report = circuit.fault_simulate(4);
+        assert_eq!(report.detected.len(), 8);
+        assert!(report.undetected.is_empty());
+        assert_eq!(report.coverage(), 100.0);
+
+        // only ever driving both inputs high can't tell stuck-at-1 from good
+        let (circuit, ids) = and_gate(1);
+        let report = circuit.fault_simulate(4);
+        assert_eq!(report.coverage(), 50.0);
+        assert_eq!(
+            report.undetected,
+            ids.map(|signal| Fault {
+                signal,
+                stuck_at: true
+            })
+        );
+        assert!(report
+            .detected
+            .iter()
+            .all(|(fault, sample)| !fault.stuck_at && *sample == 0));
+
+        // the kernel and forces of the circuit carry over, b held high hides its
+        // stuck-at-1 fault
+        let (circuit, [_, b, _, _]) = and_gate(4);
+        let mut circuit = circuit.with_kernel(Kernel::Levelized);
+        circuit.force(b, Signal::True).unwrap();
+        let report = circuit.fault_simulate(4);
+        assert_eq!(
+            report.undetected,
+            [Fault {
+                signal: b,
+                stuck_at: true
+            }]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use super::operation::SignalID;
+use super::signal::Signal;
+
+/// Signals pinned to a value regardless of what their operations compute, like
+/// `force` in Verilog. A forced signal holds its value on every lane.
+#[derive(Debug, Clone, Default)]
+pub struct Forces {
+    values: BTreeMap<SignalID, Signal>,
+}
+
+impl Forces {
+    pub fn insert(&mut self, id: SignalID, value: Signal) {
+        self.values.insert(id, value);
+    }
+
+    /// Returns whether the signal was forced.
+    pub fn remove(&mut self, id: SignalID) -> bool {
+        self.values.remove(&id).is_some()
+    }
+
+    pub fn get(&self, id: SignalID) -> Option<Signal> {
+        self.values.get(&id).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SignalID, Signal)> + '_ {
+        self.values.iter().map(|(id, value)| (*id, *value))
+    }
+
+    /// Overwrites every forced signal in a freshly computed state.
+    pub fn apply(&self, signals: &mut [Signal], lanes: usize) {
+        for (id, value) in self.values.iter() {
+            signals[id.0 * lanes..(id.0 + 1) * lanes].fill(*value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForceError {
+    /// No net with this hierarchical name is known, or the circuit has no names.
+    UnknownName(String),
+    /// The ID is past the end of the description.
+    OutOfRange(SignalID),
+}
+
+impl Display for ForceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownName(name) => write!(f, "no net is named {name}"),
+            Self::OutOfRange(id) => write!(f, "signal {} does not exist", id.0),
+        }
+    }
+}
+
+impl std::error::Error for ForceError {}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use super::operation::SignalID;
+use super::signal::Signal;
+
+/// A net which changed more than once while a circuit settled between two input
+/// samples, the mark of a hazard in combinational logic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glitch {
+    pub signal: SignalID,
+    pub lane: usize,
+    /// the value the net held when the input window began
+    pub initial: Signal,
+    /// every change in the window, the tick the new value appeared on and the value
+    pub toggles: Vec<(u128, Signal)>,
+}
+
+impl Glitch {
+    /// The first and last tick the net changed on.
+    pub fn ticks(&self) -> (u128, u128) {
+        (self.toggles[0].0, self.toggles[self.toggles.len() - 1].0)
+    }
+}
+
+impl Display for Glitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (first, last) = self.ticks();
+        write!(
+            f,
+            "glitch on signal {} lane {} between ticks {first} and {last}: {:?}",
+            self.signal.0, self.lane, self.initial
+        )?;
+        self.toggles
+            .iter()
+            .try_for_each(|(_, value)| write!(f, " -> {value:?}"))
+    }
+}
+
+/// Watches every net for more than one change within a single input window.
+///
+/// A window covers the ticks from one input sample up to the next, so the change an
+/// input sample causes counts as the first change of its window.
+#[derive(Debug, Default)]
+pub struct GlitchDetector {
+    /// the changes of every net and lane which changed in the current window, keyed
+    /// by their position in `signals`
+    changes: BTreeMap<usize, (Signal, Vec<(u128, Signal)>)>,
+    /// every glitch reported since last taken
+    pub found: Vec<Glitch>,
+}
+
+impl GlitchDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the current window, used when the state is modified outside of `tick`.
+    pub fn reset(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Feeds the state after a tick to the detector, `tick` being the tick the state
+    /// belongs to. The glitches of the window are added to `found` once its last tick
+    /// was run.
+    pub fn observe(
+        &mut self,
+        tick: u128,
+        ticks_per_input: usize,
+        lanes: usize,
+        signals: &[Signal],
+        previous: &[Signal],
+    ) {
+        for (position, (now, before)) in signals.iter().zip(previous.iter()).enumerate() {
+            if now != before {
+                let (_, toggles) = self
+                    .changes
+                    .entry(position)
+                    .or_insert((*before, Vec::new()));
+                toggles.push((tick, *now));
+            }
+        }
+
+        if tick % ticks_per_input as u128 != 0 {
+            return;
+        }
+        let glitches = std::mem::take(&mut self.changes)
+            .into_iter()
+            .filter(|(_, (_, toggles))| toggles.len() > 1)
+            .map(|(position, (initial, toggles))| Glitch {
+                signal: SignalID(position / lanes),
+                lane: position % lanes,
+                initial,
+                toggles,
+            });
+        self.found.extend(glitches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::signal::Signal;
+    use crate::back_end::circuit::stimulus::Stimulus;
+    use crate::back_end::circuit::Circuit;
+
+    #[test]
+    fn static_hazard() {
+        const TPI: usize = 4;
+
+        // a and not a is always false, but the inverter lags behind a by a tick
+        let mut gld = GateLevelDescription::new();
+        let a = gld.rz_alloc();
+        let not_a = gld.rz_alloc();
+        let y = gld.rz_alloc();
+        gld.mk_stimulus(
+            a,
+            Stimulus::Clock {
+                low: 1,
+                high: 1,
+                phase: 0,
+            },
+        )
+        .unwrap();
+        gld.mk_not(not_a, a).unwrap();
+        gld.mk_and(y, a, not_a).unwrap();
+
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), TPI).with_glitch_detection();
+        circuit.run_for(2 * TPI as u128);
+        circuit.take_glitches();
+
+        // only rising edges of a get through to y before the inverter catches up
+        circuit.run_for(4 * TPI as u128);
+        let glitches = circuit.take_glitches();
+        assert_eq!(glitches.len(), 2);
+        for (glitch, window) in glitches.iter().zip([3, 5]) {
+            let start = (window * TPI) as u128;
+            assert_eq!(glitch.signal, y);
+            assert_eq!(glitch.lane, 0);
+            assert_eq!(glitch.initial, Signal::False);
+            assert_eq!(
+                glitch.toggles,
+                [
+                    (start + 2, Signal::UncontrolledTrue),
+                    (start + 3, Signal::UncontrolledFalse)
+                ]
+            );
+            assert_eq!(glitch.ticks(), (start + 2, start + 3));
+        }
+    }
+}
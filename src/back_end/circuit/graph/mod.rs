@@ -0,0 +1,186 @@
+use super::operation::{Operation, ReadMode, SignalID};
+
+/// Splits a description into strongly connected components, following signals from
+/// the operations which drive them to the operations which read them.
+///
+/// Components are returned in data flow order, so every component only reads
+/// signals of components before it or of itself.
+pub fn components(description: &[Operation]) -> Vec<Vec<usize>> {
+    components_where(description, |_| true)
+}
+
+/// Like `components` but only following the inputs of operations for which
+/// `follows` holds, the rest are treated as reading nothing.
+pub fn components_where(
+    description: &[Operation],
+    follows: impl Fn(usize) -> bool,
+) -> Vec<Vec<usize>> {
+    components_reading(description, |index| match follows(index) {
+        true => description[index].inputs(),
+        false => Vec::new(),
+    })
+}
+
+/// Like `components` but with `reads` giving the signals each operation is taken
+/// to read in place of all of its inputs.
+pub fn components_reading(
+    description: &[Operation],
+    reads: impl Fn(usize) -> Vec<SignalID>,
+) -> Vec<Vec<usize>> {
+    let mut fanout = vec![Vec::new(); description.len()];
+    for index in 0..description.len() {
+        for source in reads(index) {
+            fanout[source.0].push(index);
+        }
+    }
+
+    // Tarjan's algorithm, iterative since feedback paths in large designs can be
+    // far deeper than the stack allows
+    const UNVISITED: usize = usize::MAX;
+    let mut order = vec![UNVISITED; description.len()];
+    let mut low = vec![0; description.len()];
+    let mut on_stack = vec![false; description.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut counter = 0;
+
+    for root in 0..description.len() {
+        if order[root] != UNVISITED {
+            continue;
+        }
+        // each frame is an operation and how far through its fanout we are
+        let mut frames = vec![(root, 0)];
+        order[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, next)) = frames.last_mut() {
+            let node = *node;
+            if let Some(&reader) = fanout[node].get(*next) {
+                *next += 1;
+                if order[reader] == UNVISITED {
+                    order[reader] = counter;
+                    low[reader] = counter;
+                    counter += 1;
+                    stack.push(reader);
+                    on_stack[reader] = true;
+                    frames.push((reader, 0));
+                } else if on_stack[reader] {
+                    low[node] = low[node].min(order[reader]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some((parent, _)) = frames.last() {
+                low[*parent] = low[*parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().expect("the component root is on the stack");
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+
+    // Tarjan finishes readers before the operations driving them
+    components.reverse();
+    components
+}
+
+/// Whether a component holds a feedback path, either several operations or a
+/// single operation reading its own signal.
+pub fn is_cycle(description: &[Operation], component: &[usize]) -> bool {
+    match component {
+        [index] => description[*index].inputs().iter().any(|id| id.0 == *index),
+        _ => true,
+    }
+}
+
+/// Marks the operations which read the previous state of their inputs whatever
+/// kernel runs them: flip-flops, latches, synchronous memory reads and the buffers
+/// holding the last value of a clock. Paths through them aren't combinational.
+pub fn sequential(description: &[Operation]) -> Vec<bool> {
+    let mut sequential: Vec<bool> = (description.iter())
+        .map(|op| holds_state(description, op))
+        .collect();
+    for op in description.iter() {
+        for last_clk in last_clocks(op) {
+            sequential[last_clk] = true;
+        }
+    }
+    sequential
+}
+
+/// Every combinational loop of a description, in data flow order.
+pub fn combinational_loops(description: &[Operation]) -> Vec<Vec<usize>> {
+    let sequential = sequential(description);
+    components_where(description, |index| !sequential[index])
+        .into_iter()
+        .filter(|component| match component[..] {
+            [index] => !sequential[index] && is_cycle(description, component),
+            _ => true,
+        })
+        .collect()
+}
+
+/// Whether an operation keeps a value of its own between ticks.
+pub fn holds_state(description: &[Operation], op: &Operation) -> bool {
+    match op {
+        Operation::Dff(_) | Operation::DLatch(_, _) => true,
+        Operation::MemRead(memory, _) => matches!(
+            &description[memory.0],
+            Operation::Memory(block) if block.read == ReadMode::Synchronous
+        ),
+        Operation::Delayed(op, _) => holds_state(description, op),
+        _ => false,
+    }
+}
+
+/// The buffers holding the previous value of a clock, these must lag a tick behind
+/// or no edge would ever be seen.
+fn last_clocks(op: &Operation) -> Vec<usize> {
+    match op {
+        Operation::Dff(ff) => vec![ff.last_clk.0],
+        Operation::Memory(block) => block.clock.iter().map(|(_, last)| last.0).collect(),
+        Operation::Delayed(op, _) => last_clocks(op),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_loops_in_order() {
+        // 0 inverting itself -> 1 -> {2, 3} loop -> 4, and 5 feeding back on itself
+        let description = [
+            Operation::Not(SignalID(0)),
+            Operation::Not(SignalID(0)),
+            Operation::Nand(SignalID(1), SignalID(3)),
+            Operation::Not(SignalID(2)),
+            Operation::Not(SignalID(3)),
+            Operation::And(SignalID(5), SignalID(4)),
+        ];
+        let components = components(&description);
+        assert_eq!(
+            components,
+            vec![vec![0], vec![1], vec![2, 3], vec![4], vec![5]]
+        );
+        let cycles: Vec<bool> = (components.iter())
+            .map(|component| is_cycle(&description, component))
+            .collect();
+        assert_eq!(cycles, [true, false, true, false, true]);
+    }
+}
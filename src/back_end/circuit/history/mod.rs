@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use super::delay::DelayLine;
+use super::memory::{Memory, MemoryChange};
+use super::signal::Signal;
+
+/// A single entry in a diff, the index of a signal along with its value before and after.
+pub type Change = (usize, Signal, Signal);
+
+/// A delay line which changed during a tick, its index along with its state before
+/// and after.
+pub type DelayChange = (usize, DelayLine, DelayLine);
+
+/// The difference between two consecutive ticks.
+///
+/// Both buffers are recorded so that a record can be applied in either direction
+/// on its own. Going from tick t to t+1 `signals` moves from state t to t+1 while
+/// `signals_swap` moves from state t-1 to t.
+#[derive(Debug)]
+struct Record {
+    signals: Vec<Change>,
+    signals_swap: Vec<Change>,
+    /// writes to memories made by the tick
+    memories: Vec<MemoryChange>,
+    /// transitions scheduled or delivered by delayed operations during the tick
+    delay_lines: Vec<DelayChange>,
+}
+
+/// A bounded record of recent ticks stored as per tick deltas.
+///
+/// Records which have been stepped back over are kept until the circuit is
+/// ticked again, so it's possible to seek back and forth through the window.
+#[derive(Debug)]
+pub struct History {
+    capacity: usize,
+    /// records for the ticks leading up to the current one, oldest first
+    past: VecDeque<Record>,
+    /// records for ticks which have been stepped back over, nearest last
+    future: Vec<Record>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            past: VecDeque::with_capacity(capacity),
+            future: Vec::new(),
+        }
+    }
+
+    /// Forgets everything recorded so far.
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+    }
+
+    /// The range of ticks which can currently be reached relative to `tick`.
+    pub fn window(&self, tick: u128) -> (u128, u128) {
+        (
+            tick - self.past.len() as u128,
+            tick + self.future.len() as u128,
+        )
+    }
+
+    /// Records the step from `signals` to `next`. Must be called before the buffers
+    /// are exchanged, while `next` holds what is about to become the current state.
+    /// `signals_swap` is the diff from the previous state to `signals`, which has to
+    /// be taken before the kernel overwrites the swap buffer.
+    pub fn record(
+        &mut self,
+        signals_swap: Vec<Change>,
+        memories: Vec<MemoryChange>,
+        delay_lines: Vec<DelayChange>,
+        signals: &[Signal],
+        next: &[Signal],
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // whatever was undone is no longer reachable once the simulation moves on
+        self.future.clear();
+        if self.past.len() == self.capacity {
+            self.past.pop_front();
+        }
+
+        self.past.push_back(Record {
+            signals: diff(signals, next),
+            signals_swap,
+            memories,
+            delay_lines,
+        });
+    }
+
+    /// Rewinds the buffers by one tick, returns false if nothing was recorded.
+    pub fn step_back(
+        &mut self,
+        signals: &mut Box<[Signal]>,
+        swap: &mut Box<[Signal]>,
+        memories: &mut [Memory],
+        delay_lines: &mut [DelayLine],
+    ) -> bool {
+        let Some(record) = self.past.pop_back() else {
+            return false;
+        };
+
+        // the previous state is already sitting in swap
+        std::mem::swap(signals, swap);
+        for &(index, before, _) in record.signals.iter() {
+            swap[index] = before;
+        }
+        for &(index, before, _) in record.signals_swap.iter() {
+            swap[index] = before;
+        }
+        for &(memory, offset, before, _) in record.memories.iter().rev() {
+            memories[memory].contents[offset] = before;
+        }
+        for (index, before, _) in record.delay_lines.iter() {
+            delay_lines[*index] = before.clone();
+        }
+
+        self.future.push(record);
+        true
+    }
+
+    /// Replays one tick which was previously stepped back over, returns false if
+    /// there is nothing to replay.
+    pub fn step_forward(
+        &mut self,
+        signals: &mut Box<[Signal]>,
+        swap: &mut Box<[Signal]>,
+        memories: &mut [Memory],
+        delay_lines: &mut [DelayLine],
+    ) -> bool {
+        let Some(record) = self.future.pop() else {
+            return false;
+        };
+
+        // the current state becomes the swap
+        std::mem::swap(signals, swap);
+        for &(index, _, after) in record.signals_swap.iter() {
+            signals[index] = after;
+        }
+        for &(index, _, after) in record.signals.iter() {
+            signals[index] = after;
+        }
+        for &(memory, offset, _, after) in record.memories.iter() {
+            memories[memory].contents[offset] = after;
+        }
+        for (index, _, after) in record.delay_lines.iter() {
+            delay_lines[*index] = after.clone();
+        }
+
+        self.past.push_back(record);
+        true
+    }
+}
+
+/// Lists every signal which differs between two states.
+pub fn diff(from: &[Signal], to: &[Signal]) -> Vec<Change> {
+    from.iter()
+        .zip(to.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(index, (a, b))| (index, *a, *b))
+        .collect()
+}
+
+/// Lists every delay line which differs between two states.
+pub fn diff_delay_lines(from: &[DelayLine], to: &[DelayLine]) -> Vec<DelayChange> {
+    from.iter()
+        .zip(to.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(index, (a, b))| (index, a.clone(), b.clone()))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    /// `Circuit::with_history` was never called.
+    NotEnabled,
+    /// The requested tick is outside of the retained window.
+    OutOfWindow {
+        requested: u128,
+        oldest: u128,
+        newest: u128,
+    },
+}
+
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotEnabled => write!(f, "history is not enabled for this circuit"),
+            Self::OutOfWindow {
+                requested,
+                oldest,
+                newest,
+            } => write!(
+                f,
+                "tick {requested} is outside of the retained history {oldest}..={newest}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
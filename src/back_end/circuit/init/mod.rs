@@ -0,0 +1,136 @@
+use super::graph;
+use super::operation::Operation;
+use super::signal::Signal;
+
+/// The values signals hold before the first tick, see `Circuit::with_init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitPolicy {
+    /// Every signal starts unknown, which is the most pessimistic choice.
+    #[default]
+    Undefined,
+    AllFalse,
+    AllTrue,
+    /// Every signal starts at a known value picked by a pseudo random generator,
+    /// the same seed always gives the same state.
+    Random(u64),
+    /// Only signals which hold state start unknown, that is flip-flops, latches,
+    /// synchronous memory reads and every operation on a feedback loop. The
+    /// rest start `False`, so any unknown left after reset comes from state the
+    /// design failed to initialise.
+    UndefinedFeedback,
+}
+
+/// Builds the initial signals of a description for every lane.
+pub fn initial_signals(
+    policy: InitPolicy,
+    description: &[Operation],
+    lanes: usize,
+) -> Box<[Signal]> {
+    let length = description.len() * lanes;
+    match policy {
+        InitPolicy::Undefined => vec![Signal::Undefined; length].into_boxed_slice(),
+        InitPolicy::AllFalse => vec![Signal::False; length].into_boxed_slice(),
+        InitPolicy::AllTrue => vec![Signal::True; length].into_boxed_slice(),
+        InitPolicy::Random(seed) => {
+            let mut state = seed;
+            (0..length)
+                .map(|_| match splitmix64(&mut state) >> 63 {
+                    0 => Signal::False,
+                    _ => Signal::True,
+                })
+                .collect()
+        }
+        InitPolicy::UndefinedFeedback => {
+            let mut signals = vec![Signal::False; length];
+            let mut hold =
+                |index: usize| signals[index * lanes..(index + 1) * lanes].fill(Signal::Undefined);
+            for (index, op) in description.iter().enumerate() {
+                if graph::holds_state(description, op) {
+                    hold(index);
+                }
+            }
+            for component in graph::components(description) {
+                if graph::is_cycle(description, &component) {
+                    component.into_iter().for_each(&mut hold);
+                }
+            }
+            signals.into_boxed_slice()
+        }
+    }
+}
+
+/// A small well mixed generator, good enough for picking initial values.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::Circuit;
+    use std::sync::Arc;
+
+    #[test]
+    fn policies() {
+        // a toggle flip-flop without a reset next to a cross coupled nand latch
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let q = gld.rz_alloc();
+        let not_q = gld.rz_alloc();
+        let top = gld.rz_alloc();
+        let bottom = gld.rz_alloc();
+        gld.mk_input(
+            clk,
+            Arc::new(|_, sample| match sample % 2 {
+                0 => Signal::False,
+                _ => Signal::True,
+            }),
+        )
+        .unwrap();
+        gld.mk_dff(q, not_q, clk, None, None).unwrap();
+        gld.mk_not(not_q, q).unwrap();
+        gld.mk_nand(top, clk, bottom).unwrap();
+        gld.mk_nand(bottom, clk, top).unwrap();
+        let description = gld.into_desc().unwrap();
+
+        let feedback = initial_signals(InitPolicy::UndefinedFeedback, &description, 2);
+        let held: Vec<usize> = (0..description.len())
+            .filter(|index| feedback[index * 2] == Signal::Undefined)
+            .collect();
+        assert_eq!(held, [q.0, not_q.0, top.0, bottom.0]);
+        assert_eq!(feedback[q.0 * 2 + 1], Signal::Undefined);
+        assert_eq!(feedback[clk.0 * 2], Signal::False);
+
+        let random = initial_signals(InitPolicy::Random(7), &description, 1);
+        assert_eq!(
+            random,
+            initial_signals(InitPolicy::Random(7), &description, 1)
+        );
+        assert!(random
+            .iter()
+            .all(|s| matches!(s, Signal::True | Signal::False)));
+
+        // without a reset the flip-flop only toggles if it starts somewhere known
+        let run = |policy| {
+            let mut circuit = Circuit::new(description.clone(), 4).with_init(policy);
+            circuit.run_for(16);
+            circuit.inspect().1[q.0]
+        };
+        assert_eq!(run(InitPolicy::Undefined), Signal::Undefined);
+        assert_eq!(run(InitPolicy::UndefinedFeedback), Signal::Undefined);
+        assert_eq!(run(InitPolicy::AllFalse), Signal::False);
+        assert_eq!(run(InitPolicy::AllTrue), Signal::True);
+        assert_eq!(
+            Circuit::new(description.clone(), 4)
+                .with_init(InitPolicy::AllTrue)
+                .inspect()
+                .1,
+            &[Signal::True; 6]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use rayon::prelude::*;
+
+use super::force::Forces;
+use super::graph;
+use super::operation::{Operation, SignalID};
+use super::signal::Signal;
+use super::TickContext;
+
+/// Book keeping for the levelized kernel.
+///
+/// Operations outside of feedback loops are evaluated in data flow order, each one
+/// reading the values its inputs were given earlier in the same tick, so an acyclic
+/// cloud of logic settles in a single tick however deep it is. Everything else is
+/// registered and reads the previous state exactly like the full sweep kernel:
+/// operations on combinational loops, which keep settling one tick per gate, and
+/// operations which hold state or need to see the previous tick, such as inputs,
+/// flip-flops, latches and the clock buffers edges are found with.
+#[derive(Debug)]
+pub struct LevelizedState {
+    registered: Box<[bool]>,
+    /// the remaining operations, each level only reads registered operations and
+    /// earlier levels so every operation in a level can be evaluated in parallel
+    levels: Box<[Box<[usize]>]>,
+}
+
+impl LevelizedState {
+    pub fn new(description: &[Operation]) -> Self {
+        let mut registered = graph::sequential(description);
+        for (index, op) in description.iter().enumerate() {
+            registered[index] |= sampled(op);
+        }
+
+        // registered operations read the previous state so they break every path
+        // through them, whatever loops are left are combinational
+        let components = graph::components_where(description, |index| !registered[index]);
+        for component in components.iter() {
+            if graph::is_cycle(description, component) {
+                component.iter().for_each(|index| registered[*index] = true);
+            }
+        }
+
+        let mut level = vec![0; description.len()];
+        let mut levels: Vec<Vec<usize>> = Vec::new();
+        for index in components.into_iter().flatten() {
+            if registered[index] {
+                continue;
+            }
+            // components are in data flow order so every input has its level already
+            level[index] = (description[index].inputs().iter())
+                .filter(|source| !registered[source.0])
+                .map(|source| level[source.0] + 1)
+                .max()
+                .unwrap_or(0);
+            if levels.len() <= level[index] {
+                levels.resize(level[index] + 1, Vec::new());
+            }
+            levels[level[index]].push(index);
+        }
+
+        Self {
+            registered: registered.into_boxed_slice(),
+            levels: levels.into_iter().map(Vec::into_boxed_slice).collect(),
+        }
+    }
+
+    /// Writes the next state of the circuit into `swap`. Forced signals are
+    /// overwritten as soon as they are evaluated so later levels read the forced
+    /// value.
+    pub fn tick(&self, context: &TickContext, forces: &Forces, swap: &mut [Signal]) {
+        let lanes = context.lanes;
+        swap.par_chunks_mut(lanes)
+            .zip(self.registered.par_iter())
+            .enumerate()
+            .filter(|(_, (_, registered))| **registered)
+            .for_each(|(index, (swap, _))| {
+                for (lane, swap) in swap.iter_mut().enumerate() {
+                    *swap = context.evaluate(index, lane);
+                }
+            });
+        forces.apply(swap, lanes);
+
+        for level in self.levels.iter() {
+            let next = TickContext {
+                signals: swap,
+                ..*context
+            };
+            let values: Vec<Signal> = level
+                .par_iter()
+                .flat_map_iter(|index| (0..lanes).map(|lane| next.evaluate(*index, lane)))
+                .collect();
+            for (index, values) in level.iter().zip(values.chunks(lanes)) {
+                let swap = &mut swap[index * lanes..(index + 1) * lanes];
+                match forces.get(SignalID(*index)) {
+                    Some(value) => swap.fill(value),
+                    None => swap.copy_from_slice(values),
+                }
+            }
+        }
+    }
+
+    /// The number of operations evaluated from the previous state every tick.
+    pub fn registered(&self) -> usize {
+        self.registered.iter().filter(|r| **r).count()
+    }
+}
+
+/// Operations which are sampled or must not see values from the current tick.
+/// Outputs are included so their handlers see the same settled state that ordered
+/// outputs are delivered from.
+fn sampled(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::Input(_)
+            | Operation::LaneInput(_)
+            | Operation::Stimulus(_)
+            | Operation::Output(_, _)
+            | Operation::LaneOutput(_, _)
+            | Operation::Delayed(_, _)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::operation::{Operation, SignalID};
+    use crate::back_end::circuit::sequential::level;
+    use crate::back_end::circuit::signal::Signal;
+    use crate::back_end::circuit::{Circuit, InitPolicy, Kernel, KernelState};
+
+    const TPI: usize = 16;
+
+    /// An inverter chain, a shift register fed by it and a ring oscillator.
+    fn description() -> (Box<[Operation]>, Vec<SignalID>) {
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let data = gld.rz_alloc();
+        let chain: Vec<SignalID> = (0..9).map(|_| gld.rz_alloc()).collect();
+        let shift = [gld.rz_alloc(), gld.rz_alloc()];
+        let ring = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        gld.mk_stimulus(clk, "clock 1 1 0".parse().unwrap())
+            .unwrap();
+        gld.mk_stimulus(data, "repeat 0011".parse().unwrap())
+            .unwrap();
+        let mut previous = data;
+        for id in chain.iter() {
+            gld.mk_not(*id, previous).unwrap();
+            previous = *id;
+        }
+        gld.mk_dff(shift[0], previous, clk, None, None).unwrap();
+        gld.mk_dff(shift[1], shift[0], clk, None, None).unwrap();
+        for (k, id) in ring.iter().enumerate() {
+            gld.mk_not(*id, ring[(k + 2) % 3]).unwrap();
+        }
+        let mut watched = vec![chain[8]];
+        watched.extend(shift);
+        watched.extend(ring);
+        (gld.into_desc().unwrap(), watched)
+    }
+
+    #[test]
+    fn settles_in_one_tick() {
+        let (desc, watched) = description();
+        let [last, q0, q1, ..] = watched[..] else {
+            unreachable!()
+        };
+        let mut levelized = Circuit::new(desc, TPI).with_kernel(Kernel::Levelized);
+        let mut full_sweep = Circuit::new(description().0, TPI);
+
+        // only the inputs, the flip-flops, their clock buffers and the ring are
+        // left reading the previous state
+        let KernelState::Levelized(state) = &levelized.kernel else {
+            unreachable!()
+        };
+        assert_eq!(state.registered(), 2 + 2 + 2 + 3);
+
+        for sample in 0..12 {
+            levelized.tick();
+            full_sweep.run_for(1);
+            // the input is registered then the whole chain follows on the next tick
+            levelized.tick();
+            assert_eq!(
+                levelized.inspect().1[last.0],
+                match (sample, sample % 4) {
+                    (0, _) | (_, 1) => Signal::True,
+                    (_, 0) => Signal::UncontrolledTrue,
+                    (_, 2) => Signal::UncontrolledFalse,
+                    _ => Signal::False,
+                },
+                "sample {sample}"
+            );
+
+            levelized.run_for(TPI as u128 - 2);
+            full_sweep.run_for(TPI as u128 - 1);
+            // the full sweep captures values which are still propagating through the
+            // chain, so only the logic levels are the same
+            let values =
+                |circuit: &Circuit| [last, q0, q1].map(|id| level(circuit.inspect().1[id.0]));
+            assert_eq!(values(&levelized), values(&full_sweep), "sample {sample}");
+        }
+    }
+
+    #[test]
+    fn loops_keep_gate_delays() {
+        let (desc, watched) = description();
+        let ring = &watched[3..];
+        let mut circuit = Circuit::new(desc, TPI)
+            .with_kernel(Kernel::Levelized)
+            .with_init(InitPolicy::AllFalse);
+
+        // an odd ring of inverters still toggles one gate per tick
+        let mut seen = Vec::new();
+        for _ in 0..12 {
+            circuit.tick();
+            seen.push(
+                ring.iter()
+                    .map(|id| circuit.inspect().1[id.0])
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(seen[6..], seen[..6]);
+        assert!(seen.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+}
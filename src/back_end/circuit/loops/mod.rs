@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::graph;
+use super::operation::{Operation, SignalID};
+
+/// How a signal travelling around a loop comes back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    /// Some path around the loop inverts an odd number of times, a likely oscillator.
+    Inverting,
+    /// Every path around the loop inverts an even number of times, a likely latch or
+    /// other storage element.
+    NonInverting,
+    /// The loop passes through an operation such as an xor, a mux select or a lookup
+    /// table whose polarity depends on its other inputs.
+    Undetermined,
+}
+
+/// One operation on a feedback loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopMember {
+    pub signal: SignalID,
+    /// the operation as printed by its `Display` implementation
+    pub operation: String,
+    /// hierarchical names of the net, empty when the circuit has none
+    pub names: Vec<String>,
+}
+
+/// A strongly connected component of the combinational logic of a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedbackLoop {
+    pub members: Vec<LoopMember>,
+    pub kind: LoopKind,
+}
+
+impl Display for FeedbackLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            LoopKind::Inverting => "inverting loop, likely an oscillator",
+            LoopKind::NonInverting => "non-inverting loop, likely a latch",
+            LoopKind::Undetermined => "loop of undetermined polarity",
+        };
+        writeln!(f, "{kind} of {} operations", self.members.len())?;
+        for member in self.members.iter() {
+            write!(f, "    {}: {}", member.signal.0, member.operation)?;
+            match member.names.is_empty() {
+                true => writeln!(f)?,
+                false => writeln!(f, " ({})", member.names.join(", "))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists every combinational loop of a description. Paths through flip-flops,
+/// latches and synchronous memories are not combinational so loops through them
+/// aren't reported.
+pub fn feedback_loops(
+    description: &[Operation],
+    names: &HashMap<String, SignalID>,
+) -> Vec<FeedbackLoop> {
+    let mut names_of: HashMap<SignalID, Vec<String>> = HashMap::new();
+    for (name, id) in names.iter() {
+        names_of.entry(*id).or_default().push(name.clone());
+    }
+    names_of.values_mut().for_each(|names| names.sort());
+
+    graph::combinational_loops(description)
+        .into_iter()
+        .map(|component| FeedbackLoop {
+            kind: classify(description, &component),
+            members: (component.iter())
+                .map(|index| LoopMember {
+                    signal: SignalID(*index),
+                    operation: description[*index].to_string(),
+                    names: names_of.get(&SignalID(*index)).cloned().unwrap_or_default(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Tries to give every member a polarity relative to the first such that each edge
+/// inside the loop agrees, which is only possible if no cycle inverts an odd number
+/// of times.
+fn classify(description: &[Operation], component: &[usize]) -> LoopKind {
+    let position: HashMap<usize, usize> = (component.iter().enumerate())
+        .map(|(position, index)| (*index, position))
+        .collect();
+    // every edge within the loop as (source, reader, inverts)
+    let mut edges = Vec::new();
+    for (reader, index) in component.iter().enumerate() {
+        for (input, source) in description[*index].inputs().into_iter().enumerate() {
+            if let Some(source) = position.get(&source.0) {
+                match polarity(&description[*index], input) {
+                    Some(inverts) => edges.push((*source, reader, inverts)),
+                    None => return LoopKind::Undetermined,
+                }
+            }
+        }
+    }
+
+    let mut parity = vec![None; component.len()];
+    parity[0] = Some(false);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (source, reader, inverts) in edges.iter() {
+            let (a, b) = (parity[*source], parity[*reader]);
+            match (a, b) {
+                (Some(a), Some(b)) if a ^ inverts != b => return LoopKind::Inverting,
+                (Some(a), None) => parity[*reader] = Some(a ^ inverts),
+                (None, Some(b)) => parity[*source] = Some(b ^ inverts),
+                _ => continue,
+            }
+            changed = true;
+        }
+    }
+    LoopKind::NonInverting
+}
+
+/// Whether an operation inverts the signal on one of its inputs, `None` when that
+/// depends on the values of the other inputs.
+fn polarity(op: &Operation, input: usize) -> Option<bool> {
+    match op {
+        Operation::Not(_)
+        | Operation::Nand(_, _)
+        | Operation::Nor(_, _)
+        | Operation::NandN(_)
+        | Operation::NorN(_) => Some(true),
+        Operation::Output(_, _)
+        | Operation::LaneOutput(_, _)
+        | Operation::And(_, _)
+        | Operation::Or(_, _)
+        | Operation::AndN(_)
+        | Operation::OrN(_)
+        | Operation::Resolve(_) => Some(false),
+        // the data inputs pass straight through, the select and enable don't
+        Operation::Mux(_, _, _) => match input {
+            0 => None,
+            _ => Some(false),
+        },
+        Operation::TriBuf(_, _) => match input {
+            0 => Some(false),
+            _ => None,
+        },
+        Operation::Delayed(op, _) => polarity(op, input),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::signal::Signal;
+    use crate::back_end::circuit::Circuit;
+    use std::sync::Arc;
+
+    #[test]
+    fn classifies_loops() {
+        let mut gld = GateLevelDescription::new();
+        let input = gld.rz_alloc();
+        let ring = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        let latch = [gld.rz_alloc(), gld.rz_alloc()];
+        let mixed = [gld.rz_alloc(), gld.rz_alloc()];
+        let toggle = [gld.rz_alloc(), gld.rz_alloc()];
+        gld.mk_input(input, Arc::new(|_, _| Signal::True)).unwrap();
+
+        // three inverters in a ring
+        for (k, id) in ring.iter().enumerate() {
+            gld.mk_not(*id, ring[(k + 2) % 3]).unwrap();
+        }
+        // cross coupled nands
+        gld.mk_nand(latch[0], input, latch[1]).unwrap();
+        gld.mk_nand(latch[1], input, latch[0]).unwrap();
+        // an xor feeding back through a buffer
+        gld.mk_xor(mixed[0], input, mixed[1]).unwrap();
+        gld.mk_and(mixed[1], mixed[0], mixed[0]).unwrap();
+        // a loop through a flip-flop is not combinational
+        gld.mk_dff(toggle[0], toggle[1], input, None, None).unwrap();
+        gld.mk_not(toggle[1], toggle[0]).unwrap();
+
+        let names = HashMap::from([
+            ("top.osc.a".to_string(), ring[0]),
+            ("top.osc.b".to_string(), ring[1]),
+            ("top.q".to_string(), latch[0]),
+        ]);
+        let circuit = Circuit::new(gld.into_desc().unwrap(), 4).with_names(names);
+        let loops = circuit.feedback_loops();
+
+        let summary: Vec<(Vec<SignalID>, LoopKind)> = (loops.iter())
+            .map(|l| (l.members.iter().map(|m| m.signal).collect(), l.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ring.to_vec(), LoopKind::Inverting),
+                (mixed.to_vec(), LoopKind::Undetermined),
+                (latch.to_vec(), LoopKind::NonInverting),
+            ]
+        );
+        assert_eq!(loops[0].members[0].names, ["top.osc.a"]);
+        assert_eq!(
+            loops[0].members[0].operation,
+            format!("Type: not Of: {}", ring[2].0)
+        );
+        assert!(loops[2].to_string().starts_with("non-inverting loop"));
+        assert!(loops[2].to_string().contains("(top.q)"));
+    }
+}
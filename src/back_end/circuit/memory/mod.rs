@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+use super::operation::{MemoryBlock, SignalID};
+use super::sequential::{capture, level, rising};
+use super::signal::Signal;
+
+/// A single entry written to a memory, the memory's position in `Circuit::memories`,
+/// the offset into its contents, and the value before and after.
+pub type MemoryChange = (usize, usize, Signal, Signal);
+
+/// The contents of one memory block, kept alongside the signals of the circuit.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    /// index of the `Operation::Memory` this is the storage of
+    pub op: usize,
+    pub words: usize,
+    pub width: usize,
+    /// every lane holds its own copy, lane `l` word `w` bit `b` is found at
+    /// `(l * words + w) * width + b`
+    pub contents: Box<[Signal]>,
+}
+
+impl Memory {
+    pub fn new(op: usize, block: &MemoryBlock, lanes: usize) -> Self {
+        let words = block.words();
+        Self {
+            op,
+            words,
+            width: block.width,
+            contents: vec![Signal::Undefined; lanes * words * block.width].into_boxed_slice(),
+        }
+    }
+
+    /// The contents of a single lane.
+    pub fn lane(&self, lane: usize) -> &[Signal] {
+        let size = self.words * self.width;
+        &self.contents[lane * size..(lane + 1) * size]
+    }
+
+    /// Reads one bit of a word, unknown or out of range addresses read as `Undefined`.
+    pub fn read(&self, lane: usize, address: Option<usize>, bit: usize) -> Signal {
+        match address {
+            Some(word) if word < self.words => self.lane(lane)[word * self.width + bit],
+            _ => Signal::Undefined,
+        }
+    }
+
+    /// Applies the write port of `block` for one lane given a function reading the
+    /// current value of a signal, returning everything which changed.
+    ///
+    /// When it's unknown whether a write happens, or where to, every word which
+    /// might have been written has the bits that would change made `Undefined`.
+    pub fn write(
+        &mut self,
+        index: usize,
+        lane: usize,
+        block: &MemoryBlock,
+        read: impl Fn(SignalID) -> Signal,
+    ) -> Vec<MemoryChange> {
+        let (Some(write_enable), Some((clk, last_clk))) = (block.write_enable, block.clock) else {
+            return Vec::new();
+        };
+
+        let definite = match (rising(read(clk), read(last_clk)), level(read(write_enable))) {
+            (Some(false), _) | (_, Some(false)) => return Vec::new(),
+            (Some(true), Some(true)) => true,
+            _ => false,
+        };
+        let address = decode(block.address.iter().map(|id| read(*id)));
+        let targets = match address {
+            Some(word) if word < self.words => word..word + 1,
+            Some(_) => return Vec::new(),
+            None => 0..self.words,
+        };
+        let certain = definite && address.is_some();
+
+        let base = lane * self.words * self.width;
+        let mut changes = Vec::new();
+        for word in targets {
+            for (bit, id) in block.data_in.iter().enumerate() {
+                let offset = base + word * self.width + bit;
+                let before = self.contents[offset];
+                let data = capture(read(*id));
+                let after = match certain || before == data {
+                    true => data,
+                    false => Signal::Undefined,
+                };
+                if after != before {
+                    self.contents[offset] = after;
+                    changes.push((index, offset, before, after));
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Turns address bits, least significant first, into a word index. Returns `None`
+/// if any bit is unknown or a set bit doesn't fit in a `usize`.
+pub fn decode(bits: impl Iterator<Item = Signal>) -> Option<usize> {
+    let mut address = 0;
+    for (k, bit) in bits.enumerate() {
+        if level(bit)? {
+            address |= 1usize.checked_shl(k.try_into().ok()?)?;
+        }
+    }
+    Some(address)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The ID does not belong to an `Operation::Memory`.
+    NotAMemory(SignalID),
+    /// The contents given don't fit the memory.
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    LaneOutOfRange(usize),
+}
+
+impl Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAMemory(id) => write!(f, "signal {} is not a memory", id.0),
+            Self::SizeMismatch { expected, found } => {
+                write!(f, "memory holds {expected} signals but {found} were given")
+            }
+            Self::LaneOutOfRange(lane) => write!(f, "lane {lane} out of range"),
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {}
@@ -1,81 +1,536 @@
+mod activity;
 pub mod builder;
+mod delay;
+mod depth;
+mod event;
+mod fault;
+mod force;
+mod glitch;
+mod graph;
+mod history;
+mod init;
+mod levelized;
+mod loops;
+mod memory;
 pub mod operation;
+mod oscillation;
+pub mod renumber;
+mod sequential;
 pub mod signal;
+mod sliced;
+pub mod snapshot;
+pub mod stimulus;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
 
 use rayon::prelude::*;
 
+use self::activity::ActivityCounter;
+pub use self::activity::ActivityReport;
+use self::delay::DelayLine;
+pub use self::depth::DepthReport;
+use self::event::EventState;
+pub use self::fault::FaultReport;
+pub use self::force::ForceError;
+use self::force::Forces;
+pub use self::glitch::Glitch;
+use self::glitch::GlitchDetector;
+pub use self::history::HistoryError;
+use self::history::*;
+use self::init::initial_signals;
+pub use self::init::InitPolicy;
+use self::levelized::LevelizedState;
+pub use self::loops::FeedbackLoop;
+pub use self::memory::MemoryError;
+use self::memory::*;
 use self::operation::*;
+pub use self::oscillation::Oscillation;
+use self::oscillation::*;
 use self::signal::*;
+use self::sliced::{Planes, SlicedState};
+use self::snapshot::*;
+use self::stimulus::{Stimulus, StimulusError};
 
 #[derive(Debug)]
 pub struct Circuit {
     description: Box<[Operation]>,
     signals: Box<[Signal]>,
     signals_swap: Box<[Signal]>,
+    /// the state of the bit sliced kernel, empty for the other kernels
+    planes: Planes,
+    planes_swap: Planes,
     ticks_per_input: usize,
     tick_counter: u128,
+    kernel: KernelState,
+    lanes: usize,
+    history: Option<History>,
+    oscillation: Option<OscillationDetector>,
+    glitches: Option<GlitchDetector>,
+    /// pending transitions of delayed operations, only allocated if there are any
+    delay_lines: Option<Box<[DelayLine]>>,
+    /// storage of every memory block in the order they appear in the description
+    memories: Box<[Memory]>,
+    /// signals pinned by `force`, applied on top of whatever the kernel computes
+    forces: Forces,
+    /// hierarchical net names, empty unless given with `with_names`
+    names: HashMap<String, SignalID>,
+    /// how signals are filled whenever the state is reset
+    init: InitPolicy,
+    /// every output operation in ID order, set when handlers are called in order
+    /// after each tick rather than from inside the kernel
+    ordered_outputs: Option<Box<[usize]>>,
+    /// transition counts of every net, only kept when asked for
+    activity: Option<ActivityCounter>,
+}
+
+/// The result of running a circuit until some condition is met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The condition was met after this many ticks.
+    Reached(u128),
+    /// The tick budget ran out before the condition was met.
+    BudgetExhausted(u128),
+}
+
+/// Selects how `Circuit::tick` decides which operations to evaluate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    /// Every operation is re-evaluated on every tick.
+    FullSweep,
+    /// Only operations whose inputs changed on the previous tick are re-evaluated.
+    /// Better suited to large designs where most of the circuit is idle.
+    EventDriven,
+    /// The state of every operation is kept packed in the bit sliced `SignalBlock`
+    /// encoding with one bit per lane, so gates are evaluated for 64 lanes at a time
+    /// with a handful of word operations. Inputs, outputs, state holding operations
+    /// and the rest without a packed form are still evaluated one lane at a time.
+    /// Pays off when running many lanes.
+    ///
+    /// The packed state is only unpacked when the circuit is inspected, or after
+    /// every tick while history, activity counts or detectors need it.
+    BitSliced,
+    /// Logic outside of feedback loops is evaluated in data flow order and settles
+    /// within a single tick, only combinational loops and state holding operations
+    /// keep the one tick per operation timing of the other kernels. Circuits
+    /// therefore settle sooner but don't tick for tick match the other kernels.
+    Levelized,
+}
+
+#[derive(Debug)]
+enum KernelState {
+    FullSweep,
+    EventDriven(EventState),
+    BitSliced(SlicedState),
+    Levelized(LevelizedState),
+}
+
+/// Everything an operation needs to read in order to compute its next value.
+struct TickContext<'a> {
+    description: &'a [Operation],
+    signals: &'a [Signal],
+    /// read in place of `signals` by the bit sliced kernel
+    planes: Option<&'a Planes>,
+    tick_counter: u128,
+    ticks_per_input: usize,
+    lanes: usize,
+    memories: &'a [Memory],
+    /// when set output handlers are left for `deliver` to call
+    defer_outputs: bool,
+}
+
+impl TickContext<'_> {
+    /// Reads the current value of a signal on the given lane.
+    fn read(&self, id: SignalID, lane: usize) -> Signal {
+        match self.planes {
+            Some(planes) => planes.get(id, lane),
+            None => self.signals[id.0 * self.lanes + lane],
+        }
+    }
+
+    /// Computes the value an operation will take on the next tick given the current
+    /// state of the circuit. Output handlers are called from here.
+    ///
+    /// For delayed operations this is the value the operation computes before the
+    /// delay is applied.
+    fn evaluate(&self, index: usize, lane: usize) -> Signal {
+        self.evaluate_op(&self.description[index], index, lane)
+    }
+
+    fn evaluate_op(&self, op: &Operation, index: usize, lane: usize) -> Signal {
+        let current = self.read(SignalID(index), lane);
+        match op {
+            // I/O port handling
+            Operation::Input(input) => self.sample(current, || {
+                input.handler.as_ref()(
+                    index,
+                    /*
+                    The division here is needed in order to split the Input tick space and Simulation tick space.
+                    If this was not present input closures would have to be aware of the depth of the circuit
+                    calling them or risk samping issues. The circuit may only call the input closure every TPI
+                    ticks so that uncontrolled singals are allowed time to propagate through the circuit.
+
+                    For example if an input changed between true and false with a period of TPI the value would
+                    appear constant true to the circuit as the closure woulldn't be called when the value was
+                    false.
+                    */
+                    self.tick_counter / self.ticks_per_input as u128,
+                )
+            }),
+            Operation::Stimulus(stimulus) => self.sample(current, || {
+                stimulus.value(self.tick_counter / self.ticks_per_input as u128)
+            }),
+            Operation::LaneInput(input) => self.sample(current, || {
+                input.handler.as_ref()(
+                    index,
+                    self.tick_counter / self.ticks_per_input as u128,
+                    lane,
+                )
+            }),
+            Operation::Output(a, output) => {
+                let value = self.read(*a, lane);
+                if !self.defer_outputs {
+                    output.handler.as_ref()(index, self.tick_counter, value); // user writes function to handle the resulting value
+                }
+                value
+            }
+            Operation::LaneOutput(a, output) => {
+                let value = self.read(*a, lane);
+                if !self.defer_outputs {
+                    output.handler.as_ref()(index, self.tick_counter, lane, value);
+                }
+                value
+            }
+
+            // standard boolean logic handling
+            Operation::Not(a) => !self.read(*a, lane),
+            Operation::And(a, b) => self.read(*a, lane) & self.read(*b, lane),
+            Operation::Nand(a, b) => !(self.read(*a, lane) & self.read(*b, lane)),
+            Operation::Or(a, b) => self.read(*a, lane) | self.read(*b, lane),
+            Operation::Nor(a, b) => !(self.read(*a, lane) | self.read(*b, lane)),
+            Operation::Xor(a, b) => self.read(*a, lane) ^ self.read(*b, lane),
+            Operation::Xnor(a, b) => !(self.read(*a, lane) ^ self.read(*b, lane)),
+            Operation::AndN(inputs) => self.reduce(inputs, lane, Signal::True, |a, b| a & b),
+            Operation::NandN(inputs) => !self.reduce(inputs, lane, Signal::True, |a, b| a & b),
+            Operation::OrN(inputs) => self.reduce(inputs, lane, Signal::False, |a, b| a | b),
+            Operation::NorN(inputs) => !self.reduce(inputs, lane, Signal::False, |a, b| a | b),
+            Operation::XorN(inputs) => self.reduce(inputs, lane, Signal::False, |a, b| a ^ b),
+            Operation::XnorN(inputs) => !self.reduce(inputs, lane, Signal::False, |a, b| a ^ b),
+            Operation::Mux(select, a, b) => {
+                let select = self.read(*select, lane);
+                (self.read(*a, lane) & !select) | (self.read(*b, lane) & select)
+            }
+            Operation::Lut(inputs, table) => {
+                let mut values = [Signal::Undefined; 6];
+                for (value, id) in values.iter_mut().zip(inputs.iter()) {
+                    *value = self.read(*id, lane);
+                }
+                Signal::lookup(*table, &values[..inputs.len()])
+            }
+            Operation::Dff(ff) => sequential::flip_flop(
+                current,
+                self.read(ff.d, lane),
+                self.read(ff.clk, lane),
+                self.read(ff.last_clk, lane),
+                ff.reset.map(|id| self.read(id, lane)),
+                ff.set.map(|id| self.read(id, lane)),
+            ),
+            Operation::DLatch(d, enable) => {
+                sequential::latch(current, self.read(*d, lane), self.read(*enable, lane))
+            }
+            Operation::Memory(_) => Signal::Undefined,
+            Operation::MemRead(memory, bit) => self.read_memory(*memory, *bit, lane, current),
+            Operation::TriBuf(data, enable) => {
+                self.read(*data, lane).tri_state(self.read(*enable, lane))
+            }
+            Operation::Resolve(drivers) => {
+                drivers.iter().fold(Signal::HighImpedance, |net, driver| {
+                    net.resolve(self.read(*driver, lane))
+                })
+            }
+            Operation::Delayed(op, _) => self.evaluate_op(op, index, lane),
+        }
+    }
+
+    /// Calls the handler of an output operation with the value it saw this tick,
+    /// exactly as the kernel would have had outputs not been deferred.
+    fn deliver(&self, op: &Operation, index: usize, lane: usize) {
+        match op {
+            Operation::Output(a, output) => {
+                output.handler.as_ref()(index, self.tick_counter, self.read(*a, lane))
+            }
+            Operation::LaneOutput(a, output) => {
+                output.handler.as_ref()(index, self.tick_counter, lane, self.read(*a, lane))
+            }
+            Operation::Delayed(op, _) => self.deliver(op, index, lane),
+            _ => (),
+        }
+    }
+
+    /// Reads one bit of the data output of a memory.
+    fn read_memory(&self, memory: SignalID, bit: usize, lane: usize, current: Signal) -> Signal {
+        let Operation::Memory(block) = &self.description[memory.0] else {
+            return Signal::Undefined;
+        };
+        let storage = match self.memories.binary_search_by_key(&memory.0, |m| m.op) {
+            Ok(t) => &self.memories[t],
+            Err(_) => return Signal::Undefined,
+        };
+
+        let address = decode(block.address.iter().map(|id| self.read(*id, lane)));
+        let value = storage.read(lane, address, bit);
+        match (block.read, block.clock) {
+            (ReadMode::Synchronous, Some((clk, last_clk))) => {
+                match sequential::rising(self.read(clk, lane), self.read(last_clk, lane)) {
+                    Some(true) => value,
+                    Some(false) => current,
+                    None => sequential::either(current, value),
+                }
+            }
+            _ => value,
+        }
+    }
+
+    /// Folds the values of several signals together starting from `identity`, which
+    /// is also the result when there are none.
+    fn reduce(
+        &self,
+        ids: &[SignalID],
+        lane: usize,
+        identity: Signal,
+        f: impl Fn(Signal, Signal) -> Signal,
+    ) -> Signal {
+        ids.iter()
+            .fold(identity, |acc, id| f(acc, self.read(*id, lane)))
+    }
+
+    /// Calls an input closure once every TPI ticks holding its value in between.
+    fn sample(&self, current: Signal, input: impl FnOnce() -> Signal) -> Signal {
+        if self.tick_counter % self.ticks_per_input as u128 == 0 {
+            let pending_input = input();
+
+            // This match statment exists to inject an uncontrolled vlaue on the leading edge
+            // of a gate transition. It's necesarry to do this as a test for uncontrolled loops
+            // in the circuit. If such a loop  exists then the uncontrolled value should
+            // dominate and thus propagate throughout the feedback path producing a stable
+            // uncontrolled output. Please run "test_case_latch" for a practical example.
+            match (pending_input, current) {
+                (Signal::True, Signal::False) => Signal::UncontrolledTrue,
+                (Signal::False, Signal::True) => Signal::UncontrolledFalse,
+                (_, _) => pending_input,
+            }
+        } else {
+            current
+        }
+    }
 }
 
 impl Circuit {
     /// Steps the circuit simulation forward one tick.
     pub fn tick(&mut self) {
-        // update self.signals_swap with pending signal values
-        self.signals_swap
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(index, swap)| match &self.description[index] {
-                // I/O port handling
-                Operation::Input(input) => {
-                    if self.tick_counter % self.ticks_per_input as u128 == 0 {
-                        let pending_input = input.handler.as_ref()(
-                            index,
-                            /*
-                            The division here is needed in order to split the Input tick space and Simulation tick space.
-                            If this was not present input closures would have to be aware of the depth of the circuit
-                            calling them or risk samping issues. The circuit may only call the input closure every TPI
-                            ticks so that uncontrolled singals are allowed time to propagate through the circuit.
-
-                            For example if an input changed between true and false with a period of TPI the value would
-                            appear constant true to the circuit as the closure woulldn't be called when the value was
-                            false.
-                            */
-                            self.tick_counter / self.ticks_per_input as u128,
-                        );
-
-                        // This match statment exists to inject an uncontrolled vlaue on the leading edge
-                        // of a gate transition. It's necesarry to do this as a test for uncontrolled loops
-                        // in the circuit. If such a loop  exists then the uncontrolled value should
-                        // dominate and thus propagate throughout the feedback path producing a stable
-                        // uncontrolled output. Please run "test_case_latch" for a practical example.
-                        *swap = match (pending_input, self.signals[index]) {
-                            (Signal::True, Signal::False) => Signal::UncontrolledTrue,
-                            (Signal::False, Signal::True) => Signal::UncontrolledFalse,
-                            (_, _) => pending_input,
-                        };
-                    } else {
-                        *swap = self.signals[index];
-                    }
+        // the bit sliced kernel only unpacks its state when something reads every tick
+        let unpack = self.history.is_some()
+            || self.activity.is_some()
+            || self.oscillation.is_some()
+            || self.glitches.is_some();
+        if unpack {
+            self.catch_up();
+        }
+        let sliced = matches!(self.kernel, KernelState::BitSliced(_));
+
+        // the swap buffer is about to be overwritten so its diff has to be taken first
+        let swap_diff = match self.history {
+            Some(_) => diff(&self.signals_swap, &self.signals),
+            None => Vec::new(),
+        };
+        let delay_lines_before = match (&self.history, &self.delay_lines) {
+            (Some(_), Some(delay_lines)) => Some(delay_lines.clone()),
+            _ => None,
+        };
+
+        let context = TickContext {
+            description: &self.description,
+            signals: &self.signals,
+            planes: sliced.then_some(&self.planes),
+            tick_counter: self.tick_counter,
+            ticks_per_input: self.ticks_per_input,
+            lanes: self.lanes,
+            memories: &self.memories,
+            defer_outputs: self.ordered_outputs.is_some(),
+        };
+
+        match &mut self.kernel {
+            KernelState::FullSweep => match &mut self.delay_lines {
+                // update self.signals_swap with pending signal values
+                None => self
+                    .signals_swap
+                    .par_chunks_mut(self.lanes)
+                    .enumerate()
+                    .for_each(|(index, swap)| {
+                        for (lane, swap) in swap.iter_mut().enumerate() {
+                            *swap = context.evaluate(index, lane);
+                        }
+                    }),
+                Some(delay_lines) => self
+                    .signals_swap
+                    .par_chunks_mut(self.lanes)
+                    .zip(delay_lines.par_chunks_mut(self.lanes))
+                    .enumerate()
+                    .for_each(|(index, (swap, lines))| {
+                        for (lane, (swap, line)) in swap.iter_mut().zip(lines).enumerate() {
+                            let computed = context.evaluate(index, lane);
+                            *swap = match &context.description[index] {
+                                Operation::Delayed(_, delay) => line.step(
+                                    context.tick_counter,
+                                    *delay,
+                                    computed,
+                                    context.read(SignalID(index), lane),
+                                ),
+                                _ => computed,
+                            };
+                        }
+                    }),
+            },
+            KernelState::EventDriven(events) => events.tick(&context, &mut self.signals_swap),
+            KernelState::BitSliced(state) => {
+                state.tick(&context, &mut self.planes_swap);
+                for (id, value) in self.forces.iter() {
+                    self.planes_swap.fill(id, value);
                 }
-                Operation::Output(a, output) => {
-                    *swap = self.signals[a.0];
-                    output.handler.as_ref()(index, self.tick_counter, *swap); // user writes function to handle the resulting value
+                if unpack {
+                    self.planes_swap.unpack_into(&mut self.signals_swap);
                 }
+            }
+            KernelState::Levelized(levelized) => {
+                levelized.tick(&context, &self.forces, &mut self.signals_swap)
+            }
+        }
 
-                // standard boolean logic handling
-                Operation::Not(a) => *swap = !self.signals[a.0],
-                Operation::And(a, b) => *swap = self.signals[a.0] & self.signals[b.0],
-                Operation::Nand(a, b) => *swap = !(self.signals[a.0] & self.signals[b.0]),
-                Operation::Or(a, b) => *swap = self.signals[a.0] | self.signals[b.0],
-                Operation::Nor(a, b) => *swap = !(self.signals[a.0] | self.signals[b.0]),
-                Operation::Xor(a, b) => *swap = self.signals[a.0] ^ self.signals[b.0],
-                Operation::Xnor(a, b) => *swap = !(self.signals[a.0] ^ self.signals[b.0]),
-            });
+        if let Some(outputs) = &self.ordered_outputs {
+            for &index in outputs.iter() {
+                for lane in 0..self.lanes {
+                    context.deliver(&self.description[index], index, lane);
+                }
+            }
+        }
+
+        self.forces.apply(&mut self.signals_swap, self.lanes);
+        if let KernelState::EventDriven(events) = &mut self.kernel {
+            events.touch(self.forces.iter().map(|(id, _)| id.0));
+        }
+
+        // memories are written after every read of this tick has been made
+        let mut memory_writes = Vec::new();
+        for (index, memory) in self.memories.iter_mut().enumerate() {
+            let Operation::Memory(block) = &self.description[memory.op] else {
+                continue;
+            };
+            for lane in 0..self.lanes {
+                memory_writes.extend(memory.write(index, lane, block, |id| match sliced {
+                    true => self.planes.get(id, lane),
+                    false => self.signals[id.0 * self.lanes + lane],
+                }));
+            }
+        }
+
+        if let Some(history) = &mut self.history {
+            let delay_changes = match (&delay_lines_before, &self.delay_lines) {
+                (Some(before), Some(after)) => diff_delay_lines(before, after),
+                _ => Vec::new(),
+            };
+            history.record(
+                swap_diff,
+                memory_writes,
+                delay_changes,
+                &self.signals,
+                &self.signals_swap,
+            );
+        }
+        if let Some(activity) = &mut self.activity {
+            activity.observe(self.lanes, &self.signals, &self.signals_swap);
+        }
 
         // exchange pointers for signals and signals_swap
         std::mem::swap(&mut self.signals, &mut self.signals_swap);
+        if let KernelState::BitSliced(state) = &mut self.kernel {
+            std::mem::swap(&mut self.planes, &mut self.planes_swap);
+            state.ahead = !unpack;
+        }
 
         // increment tick counter
         self.tick_counter += 1;
+
+        if let Some(detector) = &mut self.oscillation {
+            let sampled = (self.tick_counter - 1) % self.ticks_per_input as u128 == 0;
+            let found = detector.observe(
+                self.tick_counter,
+                sampled,
+                self.lanes,
+                &self.signals,
+                &self.signals_swap,
+            );
+            if let Some(oscillation) = found.filter(|_| detector.warn) {
+                eprintln!("warning: {oscillation}");
+            }
+        }
+        if let Some(detector) = &mut self.glitches {
+            detector.observe(
+                self.tick_counter,
+                self.ticks_per_input,
+                self.lanes,
+                &self.signals,
+                &self.signals_swap,
+            );
+        }
+    }
+
+    /// Steps the circuit simulation forward `ticks` ticks.
+    pub fn run_for(&mut self, ticks: u128) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Ticks the circuit until `predicate` holds, checking it before every tick.
+    /// Gives up after `budget` ticks.
+    pub fn run_until(
+        &mut self,
+        mut predicate: impl FnMut(&Circuit) -> bool,
+        budget: u128,
+    ) -> RunOutcome {
+        for ticks in 0..budget {
+            if predicate(self) {
+                return RunOutcome::Reached(ticks);
+            }
+            self.tick();
+        }
+        match predicate(self) {
+            true => RunOutcome::Reached(budget),
+            false => RunOutcome::BudgetExhausted(budget),
+        }
+    }
+
+    /// Ticks the circuit until a tick leaves every signal unchanged with no
+    /// delayed transition still in flight. Since only inputs can change between
+    /// samples the circuit will then hold its state until the next input sample.
+    /// Returns how many ticks it took to settle, giving up after `budget` ticks for
+    /// circuits which never settle.
+    pub fn run_until_stable(&mut self, budget: u128) -> RunOutcome {
+        for ticks in 1..=budget {
+            self.tick();
+            // memories are only written on a clock edge, which the clock buffer
+            // following the clock shows as a changed signal
+            let idle = (self.delay_lines.iter().flatten()).all(DelayLine::is_idle);
+            let settled = match &self.kernel {
+                KernelState::BitSliced(state) if state.ahead => self.planes == self.planes_swap,
+                _ => self.signals == self.signals_swap,
+            };
+            if settled && idle {
+                return RunOutcome::Reached(ticks);
+            }
+        }
+        RunOutcome::BudgetExhausted(budget)
     }
 
     /// Produces a new circuit sim object from an opperation graph structure.
@@ -83,7 +538,7 @@ impl Circuit {
     /// TPI indicates the ticks per input. AKA, how manny simulation ticks
     /// will be run in between calling input node closures. It is HIGHLY
     /// recommended that this value is longer than the propagation delay
-    /// of the circuit under testing.
+    /// of the circuit under testing, `with_auto_tpi` can work it out.
     pub fn new(description: Box<[Operation]>, tpi: usize) -> Self {
         let description_length = description.as_ref().len();
         // allocate and fill vector for initial state
@@ -100,27 +555,771 @@ impl Circuit {
         }
         assert_eq!(description_length, initial_swap.len());
 
+        let delay_lines = match description
+            .iter()
+            .any(|op| matches!(op, Operation::Delayed(_, _)))
+        {
+            true => Some(vec![DelayLine::default(); description_length].into_boxed_slice()),
+            false => None,
+        };
+
+        let memories = memories(&description, 1);
+
         return Circuit {
             description,
             signals: initial_state.into_boxed_slice(),
             signals_swap: initial_swap.into_boxed_slice(),
+            planes: Planes::default(),
+            planes_swap: Planes::default(),
             ticks_per_input: tpi,
             tick_counter: 0,
+            kernel: KernelState::FullSweep,
+            lanes: 1,
+            history: None,
+            oscillation: None,
+            glitches: None,
+            delay_lines,
+            memories,
+            forces: Forces::default(),
+            names: HashMap::new(),
+            init: InitPolicy::default(),
+            ordered_outputs: None,
+            activity: None,
         };
     }
 
+    /// Selects the simulation kernel used by `tick`. All kernels produce
+    /// identical signals on every tick apart from `Kernel::Levelized`, which
+    /// changes the timing of the circuit.
+    ///
+    /// The event driven kernel doesn't support running more than one lane.
+    pub fn with_kernel(mut self, kernel: Kernel) -> Self {
+        assert!(
+            kernel != Kernel::EventDriven || self.lanes == 1,
+            "{kernel:?} kernel does not support multiple lanes"
+        );
+        assert!(
+            kernel == Kernel::FullSweep || self.delay_lines.is_none(),
+            "{kernel:?} kernel does not support delayed operations"
+        );
+        assert!(
+            kernel != Kernel::EventDriven || self.memories.is_empty(),
+            "{kernel:?} kernel does not support memories"
+        );
+        self.catch_up();
+        (self.planes, self.planes_swap) = Default::default();
+        self.kernel = match kernel {
+            Kernel::FullSweep => KernelState::FullSweep,
+            Kernel::EventDriven => KernelState::EventDriven(EventState::new(&self.description)),
+            Kernel::BitSliced => KernelState::BitSliced(SlicedState::default()),
+            Kernel::Levelized => KernelState::Levelized(LevelizedState::new(&self.description)),
+        };
+        self.invalidate_kernel();
+        self
+    }
+
+    /// Gets the kernel selected with `with_kernel`.
+    pub fn kernel(&self) -> Kernel {
+        match self.kernel {
+            KernelState::FullSweep => Kernel::FullSweep,
+            KernelState::EventDriven(_) => Kernel::EventDriven,
+            KernelState::BitSliced(_) => Kernel::BitSliced,
+            KernelState::Levelized(_) => Kernel::Levelized,
+        }
+    }
+
+    /// Runs `lanes` independent copies of the circuit side by side. Every lane
+    /// shares the description and advances on the same tick, but holds its own
+    /// signal values. `LaneInput` and `LaneOutput` operations are told which
+    /// lane they are handling while plain inputs and outputs are called once
+    /// per lane without knowing which.
+    ///
+    /// This resets the state of the circuit so it should be called before ticking.
+    /// Recorded history, detector windows and activity counts are dropped with it.
+    pub fn with_lanes(mut self, lanes: usize) -> Self {
+        assert!(lanes > 0, "a circuit must have at least one lane");
+        assert!(
+            !matches!(self.kernel, KernelState::EventDriven(_)) || lanes == 1,
+            "the event driven kernel does not support multiple lanes"
+        );
+
+        let length = self.description.len() * lanes;
+        self.signals = initial_signals(self.init, &self.description, lanes);
+        self.signals_swap = self.signals.clone();
+        self.lanes = lanes;
+        if let Some(delay_lines) = &mut self.delay_lines {
+            *delay_lines = vec![DelayLine::default(); length].into_boxed_slice();
+        }
+        self.memories = memories(&self.description, lanes);
+        self.invalidate();
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        if self.activity.is_some() {
+            self.reset_activity();
+        }
+        self
+    }
+
+    /// Gets the stimulus of every input which has one, ready to be saved with
+    /// `stimulus::save`.
+    pub fn stimuli(&self) -> Vec<(SignalID, Stimulus)> {
+        (self.description.iter().enumerate())
+            .filter_map(|(index, op)| match op {
+                Operation::Stimulus(stimulus) => Some((SignalID(index), (**stimulus).clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Drives an input from a stimulus in place of the closure or stimulus which
+    /// drove it before, from the next input sample on. Recorded history is dropped
+    /// since it was made with the old input.
+    pub fn drive(&mut self, input: SignalID, stimulus: Stimulus) -> Result<(), StimulusError> {
+        self.check_input(input)?;
+        self.description[input.0] = Operation::Stimulus(Box::new(stimulus));
+        self.invalidate_kernel();
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        Ok(())
+    }
+
+    /// Writes the stimulus of every input which has one like `stimulus::write_to`,
+    /// but with each input given by its hierarchical name when it has one.
+    pub fn write_stimuli(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        // the first name in order when a net has several
+        let mut names: HashMap<SignalID, &str> = HashMap::new();
+        for (name, id) in self.names.iter() {
+            let first = names.entry(*id).or_insert(name);
+            *first = (*first).min(name);
+        }
+        for (id, stimulus) in self.stimuli() {
+            match names.get(&id) {
+                Some(name) => writeln!(writer, "{name} {stimulus}")?,
+                None => writeln!(writer, "{} {stimulus}", id.0)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Drives inputs from stimuli written by `write_stimuli` or `stimulus::write_to`,
+    /// binding each line to the input it gives by hierarchical name or by ID. Nothing
+    /// is driven unless every line names an input, see `drive`.
+    pub fn read_stimuli(&mut self, reader: &mut impl BufRead) -> Result<(), StimulusError> {
+        let mut bound = Vec::new();
+        for (input, stimulus) in stimulus::read_bindings(reader)? {
+            let id = (self.lookup(&input))
+                .or_else(|| input.parse().ok().map(SignalID))
+                .ok_or(StimulusError::UnknownInput(input))?;
+            self.check_input(id)?;
+            bound.push((id, stimulus));
+        }
+        for (id, stimulus) in bound {
+            self.drive(id, stimulus)?;
+        }
+        Ok(())
+    }
+
+    /// Saves stimuli with `write_stimuli` so a run can be replayed with `load_stimuli`.
+    pub fn save_stimuli(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_stimuli(&mut file)?;
+        file.flush()
+    }
+
+    /// Loads stimuli with `read_stimuli`, typically to replay a saved run on a circuit
+    /// whose inputs were built from closures.
+    pub fn load_stimuli(&mut self, path: impl AsRef<Path>) -> Result<(), StimulusError> {
+        let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+        self.read_stimuli(&mut file)
+    }
+
+    fn check_input(&self, input: SignalID) -> Result<(), StimulusError> {
+        match self.description.get(input.0) {
+            Some(Operation::Input(_) | Operation::LaneInput(_) | Operation::Stimulus(_)) => Ok(()),
+            _ => Err(StimulusError::NotAnInput(input)),
+        }
+    }
+
+    /// Calls output handlers one at a time after each tick, in `SignalID` order and
+    /// then lane order, instead of from inside the parallel kernel. Handlers see the
+    /// same values either way, but printing and channels no longer interleave
+    /// differently from run to run or with the number of threads.
+    pub fn with_ordered_outputs(mut self) -> Self {
+        self.ordered_outputs = Some(
+            (self.description.iter().enumerate())
+                .filter(|(_, op)| is_output(op))
+                .map(|(index, _)| index)
+                .collect(),
+        );
+        self
+    }
+
+    /// Counts how often every net switches from now on, see `activity`.
+    pub fn with_activity(mut self) -> Self {
+        self.reset_activity();
+        self
+    }
+
+    /// Starts a new counting window, dropping every count so far.
+    pub fn reset_activity(&mut self) {
+        self.activity = Some(ActivityCounter::new(
+            self.description.len(),
+            self.tick_counter,
+        ));
+    }
+
+    /// Ranks nets by how often they switched since counting started, along with a
+    /// rough dynamic power figure for each net and named module. Returns `None`
+    /// unless counting was enabled with `with_activity`.
+    ///
+    /// Counts only move forward, ticks undone with `step_back` are still counted.
+    pub fn activity(&self) -> Option<ActivityReport> {
+        let counter = self.activity.as_ref()?;
+        Some(ActivityReport::new(
+            counter,
+            &self.description,
+            &self.names,
+            self.tick_counter.saturating_sub(counter.start()),
+            self.ticks_per_input,
+            self.lanes,
+        ))
+    }
+
+    /// Finds the longest path from each input to each output without simulating
+    /// the circuit, see `DepthReport::critical` for the deepest of them.
+    pub fn logic_depth(&self) -> DepthReport {
+        depth::logic_depth(&self.description)
+    }
+
+    /// Replaces the ticks per input given to `new` with the depth of the critical
+    /// path plus `margin` ticks, so every output has settled before the next input
+    /// sample unless the path runs through an oscillating loop.
+    ///
+    /// The depth is that of the full sweep kernel, which is an upper bound for the
+    /// levelized kernel.
+    pub fn with_auto_tpi(mut self, margin: usize) -> Self {
+        self.ticks_per_input = self.logic_depth().ticks_per_input(margin);
+        self
+    }
+
+    /// Chooses the values signals start with, by default every signal is
+    /// `Undefined`. Starting elsewhere helps show whether a design really resets
+    /// itself rather than happening to settle from one particular state.
+    ///
+    /// This starts the circuit over from tick 0 so it should be called before ticking.
+    /// Memory contents, recorded history, detector windows and activity counts are
+    /// dropped with it, so load memories afterwards.
+    pub fn with_init(mut self, policy: InitPolicy) -> Self {
+        self.init = policy;
+        self.signals = initial_signals(policy, &self.description, self.lanes);
+        self.signals_swap = self.signals.clone();
+        self.tick_counter = 0;
+        self.memories = memories(&self.description, self.lanes);
+        self.invalidate();
+        if let Some(delay_lines) = &mut self.delay_lines {
+            delay_lines.iter_mut().for_each(DelayLine::reset);
+        }
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        if self.activity.is_some() {
+            self.reset_activity();
+        }
+        self
+    }
+
     /// Gets the current state of the simulation and its description
     /// useful if you need to inspect a snapshot of the full simulation state.
     /// For regular outputs you should prefer the usage of output Opperation closures
     /// since they will be called in paralell during the simulation loop.
+    ///
+    /// When running multiple lanes the signals of each operation are stored next
+    /// to each other, so lane `l` of signal `i` is found at `i * lanes + l`.
     pub fn inspect(&self) -> (&[Operation], &[Signal]) {
-        (self.description.as_ref(), self.signals.as_ref())
+        let signals = match &self.kernel {
+            KernelState::BitSliced(state) if state.ahead => state.view(&self.planes),
+            _ => self.signals.as_ref(),
+        };
+        (self.description.as_ref(), signals)
+    }
+
+    /// Gets the current state of a single lane of the simulation.
+    pub fn inspect_lane(&self, lane: usize) -> Vec<Signal> {
+        assert!(lane < self.lanes, "lane {lane} out of range");
+        self.inspect()
+            .1
+            .iter()
+            .skip(lane)
+            .step_by(self.lanes)
+            .copied()
+            .collect()
+    }
+
+    /// Gets the number of lanes being simulated.
+    pub fn lanes(&self) -> usize {
+        self.lanes
     }
 
     /// Gets the current tick of the simulation.
     pub fn get_tick(&self) -> u128 {
         self.tick_counter
     }
+
+    /// Captures the full simulation state so it can be saved to disk, restored
+    /// later, or restored into a second circuit to fork the simulation.
+    pub fn snapshot(&self) -> Snapshot {
+        let (signals, signals_swap) = match &self.kernel {
+            KernelState::BitSliced(state) if state.ahead => {
+                (self.planes.unpack(), self.planes_swap.unpack())
+            }
+            _ => (self.signals.clone(), self.signals_swap.clone()),
+        };
+        Snapshot {
+            operations: self.description.len(),
+            structure_hash: structure_hash(&self.description),
+            lanes: self.lanes,
+            ticks_per_input: self.ticks_per_input,
+            tick_counter: self.tick_counter,
+            signals,
+            signals_swap,
+            memories: self.memories.iter().map(|m| m.contents.clone()).collect(),
+            delay_lines: self.delay_lines.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Replaces the simulation state with one captured by `snapshot`.
+    ///
+    /// The snapshot must come from a circuit with the same description and number
+    /// of lanes, otherwise it is rejected and the circuit is left untouched.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.operations != self.description.len() {
+            return Err(SnapshotError::OperationCountMismatch {
+                expected: self.description.len(),
+                found: snapshot.operations,
+            });
+        }
+        if snapshot.structure_hash != structure_hash(&self.description) {
+            return Err(SnapshotError::StructureMismatch);
+        }
+        if snapshot.lanes != self.lanes {
+            return Err(SnapshotError::LaneMismatch {
+                expected: self.lanes,
+                found: snapshot.lanes,
+            });
+        }
+        if snapshot.ticks_per_input == 0
+            || snapshot.signals.len() != self.signals.len()
+            || snapshot.signals_swap.len() != self.signals_swap.len()
+            || snapshot.memories.len() != self.memories.len()
+            || (snapshot.memories.iter())
+                .zip(self.memories.iter())
+                .any(|(a, b)| a.len() != b.contents.len())
+            || !(snapshot.delay_lines.is_empty()
+                || Some(snapshot.delay_lines.len())
+                    == self.delay_lines.as_ref().map(|lines| lines.len()))
+        {
+            return Err(SnapshotError::Corrupt);
+        }
+
+        self.signals.copy_from_slice(&snapshot.signals);
+        self.signals_swap.copy_from_slice(&snapshot.signals_swap);
+        for (memory, contents) in self.memories.iter_mut().zip(snapshot.memories.iter()) {
+            memory.contents.copy_from_slice(contents);
+        }
+        if let Some(delay_lines) = &mut self.delay_lines {
+            match snapshot.delay_lines.is_empty() {
+                true => delay_lines.iter_mut().for_each(DelayLine::reset),
+                false => delay_lines.clone_from_slice(&snapshot.delay_lines),
+            }
+        }
+        self.tick_counter = snapshot.tick_counter;
+        self.ticks_per_input = snapshot.ticks_per_input;
+        self.invalidate();
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        Ok(())
+    }
+
+    /// Gets the contents of a memory on the first lane, see `inspect_memory_lane`.
+    pub fn inspect_memory(&self, memory: SignalID) -> Result<&[Signal], MemoryError> {
+        self.inspect_memory_lane(memory, 0)
+    }
+
+    /// Gets the contents of a memory as seen by one lane. Bit `b` of word `w` is found
+    /// at `w * width + b`.
+    pub fn inspect_memory_lane(
+        &self,
+        memory: SignalID,
+        lane: usize,
+    ) -> Result<&[Signal], MemoryError> {
+        if lane >= self.lanes {
+            return Err(MemoryError::LaneOutOfRange(lane));
+        }
+        Ok(self.memory(memory)?.lane(lane))
+    }
+
+    /// Replaces the contents of a memory on every lane, laid out as returned by
+    /// `inspect_memory`. This can't be undone so any recorded history is dropped.
+    pub fn load_memory(
+        &mut self,
+        memory: SignalID,
+        contents: &[Signal],
+    ) -> Result<(), MemoryError> {
+        let lanes = self.lanes;
+        let storage = self.memory_mut(memory)?;
+        let size = storage.words * storage.width;
+        if contents.len() != size {
+            return Err(MemoryError::SizeMismatch {
+                expected: size,
+                found: contents.len(),
+            });
+        }
+
+        for lane in 0..lanes {
+            storage.contents[lane * size..(lane + 1) * size].copy_from_slice(contents);
+        }
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        Ok(())
+    }
+
+    /// Loads a memory from integer words, bit `b` of each word is bit `b` of the data.
+    /// Words past the end of `words` are filled with `False`.
+    pub fn load_memory_words(
+        &mut self,
+        memory: SignalID,
+        words: &[u64],
+    ) -> Result<(), MemoryError> {
+        let (size, width) = {
+            let storage = self.memory(memory)?;
+            (storage.words, storage.width)
+        };
+        if words.len() > size {
+            return Err(MemoryError::SizeMismatch {
+                expected: size * width,
+                found: words.len() * width,
+            });
+        }
+
+        let mut contents = vec![Signal::False; size * width];
+        for (word, value) in words.iter().enumerate() {
+            for bit in 0..width.min(64) {
+                if value >> bit & 1 == 1 {
+                    contents[word * width + bit] = Signal::True;
+                }
+            }
+        }
+        self.load_memory(memory, &contents)
+    }
+
+    fn memory(&self, memory: SignalID) -> Result<&Memory, MemoryError> {
+        match self.memories.binary_search_by_key(&memory.0, |m| m.op) {
+            Ok(t) => Ok(&self.memories[t]),
+            Err(_) => Err(MemoryError::NotAMemory(memory)),
+        }
+    }
+
+    fn memory_mut(&mut self, memory: SignalID) -> Result<&mut Memory, MemoryError> {
+        match self.memories.binary_search_by_key(&memory.0, |m| m.op) {
+            Ok(t) => Ok(&mut self.memories[t]),
+            Err(_) => Err(MemoryError::NotAMemory(memory)),
+        }
+    }
+
+    /// Pins a signal to `value` on every lane, overriding whatever its operation
+    /// computes until it is released. The forced value is written at the end of each
+    /// tick so it shows up in `inspect` from the next tick on, and readers of the
+    /// signal see it the tick after that, just as if the operation had produced it.
+    ///
+    /// Forcing a signal again replaces the previous value.
+    pub fn force(&mut self, id: SignalID, value: Signal) -> Result<(), ForceError> {
+        if id.0 >= self.description.len() {
+            return Err(ForceError::OutOfRange(id));
+        }
+        self.forces.insert(id, value);
+        Ok(())
+    }
+
+    /// Stops forcing a signal, from the next tick on it follows its operation again.
+    /// Releasing a signal which isn't forced does nothing.
+    pub fn release(&mut self, id: SignalID) -> Result<(), ForceError> {
+        if id.0 >= self.description.len() {
+            return Err(ForceError::OutOfRange(id));
+        }
+        if self.forces.remove(id) {
+            self.catch_up();
+            self.invalidate_kernel();
+        }
+        Ok(())
+    }
+
+    /// Forces a net by its hierarchical name, see `force` and `with_names`.
+    pub fn force_named(&mut self, name: &str, value: Signal) -> Result<(), ForceError> {
+        let id = self.named(name)?;
+        self.force(id, value)
+    }
+
+    /// Releases a net by its hierarchical name, see `release` and `with_names`.
+    pub fn release_named(&mut self, name: &str) -> Result<(), ForceError> {
+        let id = self.named(name)?;
+        self.release(id)
+    }
+
+    /// Gets every forced signal along with the value it is held at.
+    pub fn forced(&self) -> impl Iterator<Item = (SignalID, Signal)> + '_ {
+        self.forces.iter()
+    }
+
+    /// Attaches hierarchical names to nets so they can be looked up by name.
+    pub fn with_names(mut self, names: HashMap<String, SignalID>) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Finds a net by its hierarchical name.
+    pub fn lookup(&self, name: &str) -> Option<SignalID> {
+        self.names.get(name).copied()
+    }
+
+    fn named(&self, name: &str) -> Result<SignalID, ForceError> {
+        self.lookup(name)
+            .ok_or_else(|| ForceError::UnknownName(name.to_string()))
+    }
+
+    /// Grades the stimulus given by the input operations against every stuck-at-0
+    /// and stuck-at-1 fault, running `samples` input samples from a fresh start
+    /// with the kernel, initial state policy and forces of this circuit. The state
+    /// of this circuit isn't touched, apart from memory contents which are copied
+    /// so loaded ROMs stay in place. See `fault::simulate` for what counts as a
+    /// detection.
+    pub fn fault_simulate(&self, samples: u128) -> FaultReport {
+        fault::simulate(self, samples)
+    }
+
+    /// Keeps a record of the last `capacity` ticks so the simulation can be stepped
+    /// backwards with `step_back` and `seek`. Each tick is stored as the set of
+    /// signals it changed.
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(History::new(capacity));
+        self
+    }
+
+    /// Rewinds the simulation by one tick using the recorded history. Past states
+    /// are rebuilt from the record so no input or output closures are called.
+    pub fn step_back(&mut self) -> Result<(), HistoryError> {
+        match self.history_window() {
+            None => Err(HistoryError::NotEnabled),
+            Some((oldest, newest)) if oldest == self.tick_counter => {
+                Err(HistoryError::OutOfWindow {
+                    requested: self.tick_counter.wrapping_sub(1),
+                    oldest,
+                    newest,
+                })
+            }
+            Some(_) => self.seek(self.tick_counter - 1),
+        }
+    }
+
+    /// Moves the simulation to any tick inside the recorded window. Ticks which were
+    /// stepped back over may be returned to until the circuit is ticked again.
+    pub fn seek(&mut self, tick: u128) -> Result<(), HistoryError> {
+        self.catch_up();
+        let Some(history) = &mut self.history else {
+            return Err(HistoryError::NotEnabled);
+        };
+
+        let (oldest, newest) = history.window(self.tick_counter);
+        if tick < oldest || tick > newest {
+            return Err(HistoryError::OutOfWindow {
+                requested: tick,
+                oldest,
+                newest,
+            });
+        }
+
+        while self.tick_counter > tick {
+            history.step_back(
+                &mut self.signals,
+                &mut self.signals_swap,
+                &mut self.memories,
+                self.delay_lines.as_deref_mut().unwrap_or_default(),
+            );
+            self.tick_counter -= 1;
+        }
+        while self.tick_counter < tick {
+            history.step_forward(
+                &mut self.signals,
+                &mut self.signals_swap,
+                &mut self.memories,
+                self.delay_lines.as_deref_mut().unwrap_or_default(),
+            );
+            self.tick_counter += 1;
+        }
+
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Gets the range of ticks which `seek` can currently reach.
+    pub fn history_window(&self) -> Option<(u128, u128)> {
+        self.history
+            .as_ref()
+            .map(|history| history.window(self.tick_counter))
+    }
+
+    /// Watches the circuit for limit cycles while it runs. Each input window in
+    /// which the circuit is found oscillating is recorded for `take_oscillations`.
+    pub fn with_oscillation_detection(mut self) -> Self {
+        self.oscillation = Some(OscillationDetector::new());
+        self
+    }
+
+    /// Like `with_oscillation_detection`, but also prints a warning for each
+    /// oscillation as it is found.
+    pub fn with_oscillation_warnings(mut self) -> Self {
+        let mut detector = OscillationDetector::new();
+        detector.warn = true;
+        self.oscillation = Some(detector);
+        self
+    }
+
+    /// Takes every oscillation recorded since `with_oscillation_detection` or
+    /// `with_oscillation_warnings` was called or this was last called.
+    pub fn take_oscillations(&mut self) -> Vec<Oscillation> {
+        match &mut self.oscillation {
+            Some(detector) => std::mem::take(&mut detector.found),
+            None => Vec::new(),
+        }
+    }
+
+    /// Watches every net for changing more than once between two input samples.
+    /// Each such net is recorded for `take_glitches` once its window is over.
+    ///
+    /// Nets settling from `Undefined` count too, so the first window after starting
+    /// from an unknown state may report glitches which are only the circuit waking up.
+    pub fn with_glitch_detection(mut self) -> Self {
+        self.glitches = Some(GlitchDetector::new());
+        self
+    }
+
+    /// Takes every glitch recorded since `with_glitch_detection` was called or this
+    /// was last called.
+    pub fn take_glitches(&mut self) -> Vec<Glitch> {
+        match &mut self.glitches {
+            Some(detector) => std::mem::take(&mut detector.found),
+            None => Vec::new(),
+        }
+    }
+
+    /// Lists every combinational loop of the circuit without simulating it, with
+    /// the hierarchical names of each net when the circuit has them. Each loop is
+    /// classed as inverting, which tends to oscillate, or non-inverting, which
+    /// tends to hold a value like a latch.
+    pub fn feedback_loops(&self) -> Vec<FeedbackLoop> {
+        loops::feedback_loops(&self.description, &self.names)
+    }
+
+    /// Ticks the circuit until it is seen to repeat a state while its inputs are
+    /// held, reporting the period of the cycle and the signals which toggle in it.
+    ///
+    /// Only cycles shorter than the ticks per input can be detected. Returns `None`
+    /// if the circuit settles or `budget` ticks pass without finding a cycle.
+    pub fn find_oscillation(&mut self, budget: u128) -> Option<Oscillation> {
+        self.catch_up();
+        let mut detector = OscillationDetector::new();
+        detector.observe(
+            self.tick_counter,
+            true,
+            self.lanes,
+            &self.signals,
+            &self.signals_swap,
+        );
+
+        for _ in 0..budget {
+            self.tick();
+            self.catch_up();
+            if self.signals == self.signals_swap {
+                return None;
+            }
+
+            let sampled = (self.tick_counter - 1) % self.ticks_per_input as u128 == 0;
+            let found = detector.observe(
+                self.tick_counter,
+                sampled,
+                self.lanes,
+                &self.signals,
+                &self.signals_swap,
+            );
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Must be called whenever the signal buffers are changed outside of `tick`
+    /// so that kernels holding book keeping about them start over.
+    fn invalidate(&mut self) {
+        self.invalidate_kernel();
+        if let Some(detector) = &mut self.oscillation {
+            detector.reset();
+        }
+        if let Some(detector) = &mut self.glitches {
+            detector.reset();
+        }
+    }
+
+    /// The event kernel only tracks changes it computed itself, so it has to start over
+    /// whenever signals are changed behind its back, and the bit sliced kernel packs
+    /// its state again from them.
+    fn invalidate_kernel(&mut self) {
+        match &mut self.kernel {
+            KernelState::EventDriven(events) => events.invalidate(),
+            KernelState::BitSliced(state) => {
+                self.planes = Planes::pack(&self.signals, self.lanes);
+                self.planes_swap = Planes::pack(&self.signals_swap, self.lanes);
+                state.ahead = false;
+            }
+            _ => (),
+        }
+    }
+
+    /// Unpacks the state of the bit sliced kernel into the signal buffers when they
+    /// are behind it. Must be called before anything reads or changes the buffers
+    /// outside of `tick`.
+    fn catch_up(&mut self) {
+        if let KernelState::BitSliced(state) = &mut self.kernel {
+            if state.ahead {
+                self.planes.unpack_into(&mut self.signals);
+                self.planes_swap.unpack_into(&mut self.signals_swap);
+                state.ahead = false;
+            }
+        }
+    }
+}
+
+fn is_output(op: &Operation) -> bool {
+    match op {
+        Operation::Output(_, _) | Operation::LaneOutput(_, _) => true,
+        Operation::Delayed(op, _) => is_output(op),
+        _ => false,
+    }
+}
+
+/// Allocates storage for every memory block in a description.
+fn memories(description: &[Operation], lanes: usize) -> Box<[Memory]> {
+    description
+        .iter()
+        .enumerate()
+        .filter_map(|(index, op)| match op {
+            Operation::Memory(block) => Some(Memory::new(index, block, lanes)),
+            _ => None,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -177,8 +1376,1150 @@ mod tests {
         ]);
 
         let mut circuit = Circuit::new(description, TPI);
-        for _ in 0..=256 {
+        circuit.run_for(257);
+    }
+
+    /// A description large enough to be split across threads, made up
+    /// of every gate type wired together with plenty of feedback.
+    fn mixed_description() -> Box<[Operation]> {
+        use std::sync::Arc;
+
+        const SIZE: usize = 200;
+        let mut description = vec![
+            Operation::Input(InputHandler::new(Arc::new(|_index, tick| {
+                match (tick / 3) % 2 {
+                    0 => Signal::False,
+                    _ => Signal::True,
+                }
+            }))),
+            Operation::Input(InputHandler::new(Arc::new(|_index, tick| {
+                match (tick / 5) % 2 {
+                    0 => Signal::True,
+                    _ => Signal::False,
+                }
+            }))),
+        ];
+        for index in description.len()..SIZE {
+            // mostly feed forward so known values reach deep into the circuit,
+            // with every tenth gate feeding back from further along
+            let a = SignalID((index * 7 + 3) % index);
+            let b = match index % 10 {
+                0 => SignalID((index * 13 + 1) % SIZE),
+                _ => SignalID((index * 13 + 1) % index),
+            };
+            description.push(match index % 8 {
+                0 => Operation::Not(a),
+                1 => Operation::And(a, b),
+                2 => Operation::Nand(a, b),
+                3 => Operation::Or(a, b),
+                4 => Operation::Nor(a, b),
+                5 => Operation::Xor(a, b),
+                6 => Operation::Xnor(a, b),
+                _ => Operation::Output(a, OutputHandler::new(Arc::new(|_, _, _| ()))),
+            });
+        }
+        description.into_boxed_slice()
+    }
+
+    #[test]
+    fn kernels_match_full_sweep() {
+        const TPI: usize = 6;
+
+        let mut full_sweep = Circuit::new(mixed_description(), TPI);
+        let mut event_driven =
+            Circuit::new(mixed_description(), TPI).with_kernel(Kernel::EventDriven);
+        let mut bit_sliced = Circuit::new(mixed_description(), TPI).with_kernel(Kernel::BitSliced);
+
+        for _ in 0..256 {
+            full_sweep.tick();
+            event_driven.tick();
+            bit_sliced.tick();
+            assert_eq!(full_sweep.inspect().1, event_driven.inspect().1);
+            assert_eq!(full_sweep.signals_swap, event_driven.signals_swap);
+            assert_eq!(full_sweep.inspect().1, bit_sliced.inspect().1);
+        }
+    }
+
+    #[test]
+    fn bit_sliced_lanes_match_full_sweep() {
+        use std::sync::Arc;
+
+        const TPI: usize = 6;
+        // more lanes than fit in one block, leaving most of the second one unused
+        const LANES: usize = 70;
+
+        let description = || {
+            let mut description = mixed_description().into_vec();
+            description[0] = Operation::LaneInput(LaneInputHandler::new(Arc::new(
+                |_index, tick, lane| match (tick / (lane as u128 % 7 + 1)) % 2 {
+                    0 => Signal::False,
+                    _ => Signal::True,
+                },
+            )));
+            let inputs: Box<[SignalID]> = Box::new([SignalID(0), SignalID(1), SignalID(50)]);
+            description.extend([
+                Operation::AndN(inputs.clone()),
+                Operation::XnorN(inputs),
+                Operation::Mux(SignalID(0), SignalID(30), SignalID(41)),
+            ]);
+            description.into_boxed_slice()
+        };
+        let mut full_sweep = Circuit::new(description(), TPI).with_lanes(LANES);
+        let mut bit_sliced = Circuit::new(description(), TPI)
+            .with_lanes(LANES)
+            .with_kernel(Kernel::BitSliced);
+
+        let compare = |full_sweep: &mut Circuit, bit_sliced: &mut Circuit, ticks: usize| {
+            for _ in 0..ticks {
+                full_sweep.tick();
+                bit_sliced.tick();
+                assert_eq!(full_sweep.inspect().1, bit_sliced.inspect().1);
+            }
+            assert_eq!(full_sweep.inspect_lane(69), bit_sliced.inspect_lane(69));
+        };
+        compare(&mut full_sweep, &mut bit_sliced, 64);
+
+        full_sweep.force(SignalID(30), Signal::False).unwrap();
+        bit_sliced.force(SignalID(30), Signal::False).unwrap();
+        compare(&mut full_sweep, &mut bit_sliced, 16);
+        full_sweep.release(SignalID(30)).unwrap();
+        bit_sliced.release(SignalID(30)).unwrap();
+        compare(&mut full_sweep, &mut bit_sliced, 16);
+
+        // a snapshot taken while the planes are ahead holds the current state
+        let snapshot = bit_sliced.snapshot();
+        assert_eq!(snapshot, full_sweep.snapshot());
+        compare(&mut full_sweep, &mut bit_sliced, 8);
+        full_sweep.restore(&snapshot).unwrap();
+        bit_sliced.restore(&snapshot).unwrap();
+        compare(&mut full_sweep, &mut bit_sliced, 8);
+
+        // history turned on mid run unpacks every tick from then on
+        let mut bit_sliced = bit_sliced.with_history(16);
+        let mut states = vec![bit_sliced.inspect().1.to_vec()];
+        for _ in 0..8 {
+            bit_sliced.tick();
+            states.push(bit_sliced.inspect().1.to_vec());
+        }
+        for state in states.iter().rev().skip(1) {
+            bit_sliced.step_back().unwrap();
+            assert_eq!(bit_sliced.inspect().1, state.as_slice());
+        }
+        compare(&mut full_sweep, &mut bit_sliced, 8);
+    }
+
+    #[test]
+    fn with_lanes_starts_instrumentation_over() {
+        let mut circuit = Circuit::new(mixed_description(), 6)
+            .with_history(16)
+            .with_glitch_detection()
+            .with_activity();
+        circuit.run_for(10);
+
+        let mut circuit = circuit.with_lanes(3);
+        assert_eq!(circuit.history_window(), Some((10, 10)));
+        assert_eq!(circuit.activity().unwrap().ticks, 0);
+
+        let mut states = vec![circuit.signals.clone()];
+        for _ in 0..8 {
+            circuit.tick();
+            states.push(circuit.signals.clone());
+        }
+        for state in states.iter().rev().skip(1) {
+            circuit.step_back().unwrap();
+            assert_eq!(&circuit.signals, state);
+        }
+    }
+
+    #[test]
+    fn with_init_starts_over() {
+        use crate::back_end::circuit::builder::GateLevelDescription;
+        use std::sync::Arc;
+
+        let mut gld = GateLevelDescription::new();
+        let [address, rom, data] = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+        let alternate = |_, sample| match sample % 2 {
+            0 => Signal::False,
+            _ => Signal::True,
+        };
+        gld.mk_input(address, Arc::new(alternate)).unwrap();
+        gld.mk_rom(rom, &[data], &[address], None).unwrap();
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), 2)
+            .with_history(16)
+            .with_activity();
+        circuit
+            .load_memory(rom, &[Signal::True, Signal::False])
+            .unwrap();
+        circuit.run_for(10);
+
+        let mut circuit = circuit.with_init(InitPolicy::AllFalse);
+        assert_eq!(circuit.tick_counter, 0);
+        assert_eq!(circuit.history_window(), Some((0, 0)));
+        assert_eq!(circuit.activity().unwrap().ticks, 0);
+        assert_eq!(
+            circuit.inspect_memory(rom).unwrap(),
+            &[Signal::Undefined; 2]
+        );
+
+        circuit.tick();
+        circuit.step_back().unwrap();
+        assert_eq!(circuit.inspect().1, &[Signal::False; 3]);
+    }
+
+    #[test]
+    fn lanes_match_independent_circuits() {
+        use std::sync::{Arc, Mutex};
+
+        const TPI: usize = 4;
+        const LANES: usize = 5;
+
+        // each lane drives the set input of a nor latch with a different period
+        fn set(tick: u128, lane: usize) -> Signal {
+            match (tick / (lane as u128 + 1)) % 2 {
+                0 => Signal::False,
+                _ => Signal::True,
+            }
+        }
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let lane_reports = reports.clone();
+        let description = |input: Operation| -> Box<[Operation]> {
+            Box::new([
+                input,
+                Operation::Input(InputHandler::new(Arc::new(|_index, tick| match tick % 5 {
+                    0 => Signal::True,
+                    _ => Signal::False,
+                }))),
+                Operation::Nor(SignalID(0), SignalID(3)),
+                Operation::Nor(SignalID(1), SignalID(2)),
+            ])
+        };
+
+        let mut laned = Circuit::new(
+            description(Operation::LaneInput(LaneInputHandler::new(Arc::new(
+                |_index, tick, lane| set(tick, lane),
+            ))))
+            .into_vec()
+            .into_iter()
+            .chain([Operation::LaneOutput(
+                SignalID(2),
+                LaneOutputHandler::new(Arc::new(move |_index, tick, lane, signal| {
+                    lane_reports.lock().unwrap().push((tick, lane, signal))
+                })),
+            )])
+            .collect(),
+            TPI,
+        )
+        .with_lanes(LANES);
+
+        let mut independent: Vec<Circuit> = (0..LANES)
+            .map(|lane| {
+                Circuit::new(
+                    description(Operation::Input(InputHandler::new(Arc::new(
+                        move |_index, tick| set(tick, lane),
+                    )))),
+                    TPI,
+                )
+            })
+            .collect();
+
+        for _ in 0..128 {
+            laned.tick();
+            for (lane, circuit) in independent.iter_mut().enumerate() {
+                circuit.tick();
+                assert_eq!(&laned.inspect_lane(lane)[..4], circuit.inspect().1);
+            }
+        }
+
+        // the output reports every lane on every tick
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 128 * LANES);
+        for lane in 0..LANES {
+            assert!(reports.contains(&(127, lane, laned.inspect_lane(lane)[4])));
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trip_and_fork() {
+        const TPI: usize = 6;
+
+        let mut original = Circuit::new(mixed_description(), TPI).with_kernel(Kernel::EventDriven);
+        for _ in 0..37 {
+            original.tick();
+        }
+
+        let mut file = Vec::new();
+        original.snapshot().write_to(&mut file).unwrap();
+        let snapshot = Snapshot::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(snapshot, original.snapshot());
+        assert_eq!(snapshot.get_tick(), 37);
+
+        let mut fork = Circuit::new(mixed_description(), TPI);
+        fork.restore(&snapshot).unwrap();
+        for _ in 0..64 {
+            original.tick();
+            fork.tick();
+            assert_eq!(original.inspect().1, fork.inspect().1);
+            assert_eq!(original.get_tick(), fork.get_tick());
+        }
+    }
+
+    #[test]
+    fn snapshot_and_seek_keep_delays_in_flight() {
+        use std::sync::Arc;
+
+        const TPI: usize = 3;
+
+        let description = || -> Box<[Operation]> {
+            Box::new([
+                Operation::Input(InputHandler::new(Arc::new(|_, sample| match sample % 2 {
+                    0 => Signal::True,
+                    _ => Signal::False,
+                }))),
+                Operation::Delayed(Box::new(Operation::Not(SignalID(0))), Delay::transport(5)),
+            ])
+        };
+
+        let mut original = Circuit::new(description(), TPI).with_history(32);
+        original.run_for(10);
+
+        let mut file = Vec::new();
+        original.snapshot().write_to(&mut file).unwrap();
+        let snapshot = Snapshot::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(snapshot, original.snapshot());
+        assert!(snapshot
+            .delay_lines
+            .iter()
+            .any(|line| !line.pending.is_empty()));
+
+        let mut fork = Circuit::new(description(), TPI);
+        fork.restore(&snapshot).unwrap();
+        let mut trace = Vec::new();
+        for _ in 0..20 {
+            original.tick();
+            fork.tick();
+            assert_eq!(original.inspect().1, fork.inspect().1);
+            trace.push(original.signals.clone());
+        }
+
+        // stepping back restores the transitions which were in flight too
+        original.seek(15).unwrap();
+        for signals in trace[5..].iter() {
+            original.tick();
+            assert_eq!(&original.signals, signals);
+        }
+    }
+
+    #[test]
+    fn snapshot_rejects_other_circuits() {
+        let snapshot = Circuit::new(mixed_description(), 6).snapshot();
+
+        let mut shorter = Circuit::new(mixed_description()[..100].into(), 6);
+        assert!(matches!(
+            shorter.restore(&snapshot),
+            Err(SnapshotError::OperationCountMismatch {
+                expected: 100,
+                found: 200
+            })
+        ));
+
+        let mut rewired = mixed_description();
+        rewired[50] = Operation::Not(SignalID(3));
+        let mut rewired = Circuit::new(rewired, 6);
+        assert!(matches!(
+            rewired.restore(&snapshot),
+            Err(SnapshotError::StructureMismatch)
+        ));
+
+        let mut file = Vec::new();
+        snapshot.write_to(&mut file).unwrap();
+        file[0] = b'X';
+        assert!(matches!(
+            Snapshot::read_from(&mut file.as_slice()),
+            Err(SnapshotError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn step_back_and_seek() {
+        const TPI: usize = 6;
+        const CAPACITY: usize = 16;
+
+        let mut circuit = Circuit::new(mixed_description(), TPI)
+            .with_kernel(Kernel::EventDriven)
+            .with_history(CAPACITY);
+        let mut states = vec![(circuit.signals.clone(), circuit.signals_swap.clone())];
+        for _ in 0..40 {
             circuit.tick();
+            states.push((circuit.signals.clone(), circuit.signals_swap.clone()));
+        }
+        assert_eq!(circuit.history_window(), Some((24, 40)));
+
+        for tick in (24..40).rev() {
+            circuit.step_back().unwrap();
+            assert_eq!(circuit.get_tick(), tick);
+            assert_eq!(circuit.signals, states[tick as usize].0);
+            assert_eq!(circuit.signals_swap, states[tick as usize].1);
         }
+        assert_eq!(
+            circuit.step_back(),
+            Err(HistoryError::OutOfWindow {
+                requested: 23,
+                oldest: 24,
+                newest: 40
+            })
+        );
+
+        circuit.seek(33).unwrap();
+        assert_eq!(circuit.signals, states[33].0);
+        assert_eq!(circuit.signals_swap, states[33].1);
+        circuit.seek(40).unwrap();
+        assert_eq!(circuit.signals, states[40].0);
+
+        // ticking after stepping back carries on from the past state
+        circuit.seek(30).unwrap();
+        for (signals, _) in states[31..].iter() {
+            circuit.tick();
+            assert_eq!(&circuit.signals, signals);
+        }
+        for _ in 41..=45 {
+            circuit.tick();
+        }
+        assert_eq!(circuit.history_window(), Some((29, 45)));
+        assert!(
+            Circuit::new(mixed_description(), TPI).step_back() == Err(HistoryError::NotEnabled)
+        );
+    }
+
+    #[test]
+    fn run_until_and_stable() {
+        use std::sync::Arc;
+
+        const TPI: usize = 16;
+
+        // an inverter chain settles while a ring oscillator never does
+        let chain: Box<[Operation]> = Box::new([
+            Operation::Input(InputHandler::new(Arc::new(|_index, _tick| Signal::True))),
+            Operation::Not(SignalID(0)),
+            Operation::Not(SignalID(1)),
+            Operation::Not(SignalID(2)),
+        ]);
+        let mut circuit = Circuit::new(chain.clone(), TPI);
+        assert_eq!(
+            circuit.run_until_stable(TPI as u128),
+            RunOutcome::Reached(5)
+        );
+        assert_eq!(circuit.inspect().1[3], Signal::False);
+
+        let mut circuit = Circuit::new(chain, TPI);
+        assert_eq!(
+            circuit.run_until(|c| c.inspect().1[2] == Signal::True, 100),
+            RunOutcome::Reached(3)
+        );
+        assert_eq!(
+            circuit.run_until(|c| c.inspect().1[2] == Signal::False, 100),
+            RunOutcome::BudgetExhausted(100)
+        );
+        assert_eq!(circuit.get_tick(), 103);
+
+        // a transition in flight on a delay keeps the circuit from being stable
+        let delayed: Box<[Operation]> = Box::new([
+            Operation::Input(InputHandler::new(Arc::new(|_index, _tick| Signal::True))),
+            Operation::Delayed(Box::new(Operation::Not(SignalID(0))), Delay::transport(5)),
+        ]);
+        let mut circuit = Circuit::new(delayed, TPI);
+        assert_eq!(
+            circuit.run_until_stable(TPI as u128),
+            RunOutcome::Reached(7)
+        );
+        assert_eq!(circuit.inspect().1[1], Signal::False);
+
+        let ring: Box<[Operation]> = Box::new([
+            Operation::Not(SignalID(2)),
+            Operation::Not(SignalID(0)),
+            Operation::Not(SignalID(1)),
+        ]);
+        // kick the ring out of its all undefined state
+        let mut circuit = Circuit::new(ring, TPI);
+        circuit
+            .restore(&{
+                let mut snapshot = circuit.snapshot();
+                snapshot.signals[0] = Signal::True;
+                snapshot
+            })
+            .unwrap();
+        assert_eq!(
+            circuit.run_until_stable(64),
+            RunOutcome::BudgetExhausted(64)
+        );
+    }
+
+    #[test]
+    fn ring_oscillator_is_detected() {
+        use std::sync::Arc;
+
+        const TPI: usize = 64;
+
+        let description: Box<[Operation]> = Box::new([
+            Operation::Not(SignalID(2)),
+            Operation::Not(SignalID(0)),
+            Operation::Not(SignalID(1)),
+            Operation::Input(InputHandler::new(Arc::new(|_index, _tick| Signal::True))),
+            Operation::And(SignalID(3), SignalID(3)),
+        ]);
+        let kicked = |circuit: &Circuit| {
+            let mut snapshot = circuit.snapshot();
+            snapshot.signals[..3].copy_from_slice(&[Signal::True, Signal::False, Signal::True]);
+            snapshot
+        };
+
+        let mut circuit = Circuit::new(description.clone(), TPI);
+        circuit.restore(&kicked(&circuit)).unwrap();
+        let oscillation = circuit.find_oscillation(TPI as u128).unwrap();
+        assert_eq!(oscillation.period, 6);
+        assert_eq!(
+            oscillation.toggling,
+            vec![SignalID(0), SignalID(1), SignalID(2)]
+        );
+
+        // once per input window, whether or not warnings are printed
+        for mut circuit in [
+            Circuit::new(description.clone(), TPI).with_oscillation_warnings(),
+            Circuit::new(description, TPI).with_oscillation_detection(),
+        ] {
+            circuit.restore(&kicked(&circuit)).unwrap();
+            circuit.run_for(3 * TPI as u128);
+            let found = circuit.take_oscillations();
+            assert_eq!(found.len(), 3);
+            assert!(found.iter().all(|o| o.period == 6));
+            assert!(circuit.take_oscillations().is_empty());
+        }
+
+        // a settled circuit is not reported
+        let mut latch = Circuit::new(
+            Box::new([
+                Operation::Input(InputHandler::new(Arc::new(|_index, _tick| Signal::False))),
+                Operation::Nor(SignalID(0), SignalID(2)),
+                Operation::Nor(SignalID(0), SignalID(1)),
+            ]),
+            TPI,
+        );
+        latch
+            .restore(&{
+                let mut snapshot = latch.snapshot();
+                snapshot
+                    .signals
+                    .copy_from_slice(&[Signal::False, Signal::True, Signal::False]);
+                snapshot
+            })
+            .unwrap();
+        assert_eq!(latch.find_oscillation(TPI as u128), None);
+    }
+
+    #[test]
+    fn delays() {
+        use std::sync::Arc;
+
+        const TPI: usize = 2;
+
+        // high for a single input window then for five, starting low
+        let input = || {
+            Operation::Input(InputHandler::new(Arc::new(|_index, tick| {
+                match tick / TPI as u128 {
+                    3 | 8..=12 => Signal::True,
+                    _ => Signal::False,
+                }
+            })))
+        };
+
+        // a transport delay behaves like a chain of single tick buffers
+        let mut delayed = Circuit::new(
+            Box::new([
+                input(),
+                Operation::Delayed(Box::new(Operation::Not(SignalID(0))), Delay::transport(3)),
+            ]),
+            TPI,
+        );
+        let mut chain = Circuit::new(
+            Box::new([
+                input(),
+                Operation::Not(SignalID(0)),
+                Operation::And(SignalID(1), SignalID(1)),
+                Operation::And(SignalID(2), SignalID(2)),
+            ]),
+            TPI,
+        );
+        for _ in 0..40 {
+            delayed.tick();
+            chain.tick();
+            assert_eq!(delayed.inspect().1[1], chain.inspect().1[3]);
+        }
+
+        // an inertial delay swallows the short pulse but not the long one
+        let mut inertial = Circuit::new(
+            Box::new([
+                input(),
+                Operation::Delayed(Box::new(Operation::Not(SignalID(0))), Delay::inertial(4)),
+            ]),
+            TPI,
+        );
+        let mut trace = Vec::new();
+        for _ in 0..40 {
+            inertial.tick();
+            trace.push(inertial.inspect().1[1]);
+        }
+        let falls: Vec<usize> = trace
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] == Signal::True && pair[1] != Signal::True)
+            .map(|(tick, _)| tick)
+            .collect();
+        assert_eq!(falls.len(), 1);
+        assert!(falls[0] > 8 * TPI);
+        assert!(trace[8 * TPI..].contains(&Signal::False));
+
+        // a delay of one tick changes nothing
+        let with_delay = |delay: fn(usize) -> Delay| {
+            mixed_description()
+                .iter()
+                .map(|op| Operation::Delayed(Box::new(op.clone()), delay(1)))
+                .collect::<Box<[Operation]>>()
+        };
+        let mut plain = Circuit::new(mixed_description(), 6);
+        let mut transport = Circuit::new(with_delay(Delay::transport), 6);
+        let mut inertial = Circuit::new(with_delay(Delay::inertial), 6);
+        for _ in 0..128 {
+            plain.tick();
+            transport.tick();
+            inertial.tick();
+            assert_eq!(plain.inspect().1, transport.inspect().1);
+            assert_eq!(plain.inspect().1, inertial.inspect().1);
+        }
+    }
+
+    #[test]
+    fn tri_state_bus() {
+        use std::sync::Arc;
+
+        let constant = |signal: Signal| {
+            Operation::Input(InputHandler::new(Arc::new(move |_index, _tick| signal)))
+        };
+        let bus = |enable_a: Signal, enable_b: Signal| {
+            let mut circuit = Circuit::new(
+                Box::new([
+                    constant(Signal::True),
+                    constant(enable_a),
+                    constant(Signal::False),
+                    constant(enable_b),
+                    Operation::TriBuf(SignalID(0), SignalID(1)),
+                    Operation::TriBuf(SignalID(2), SignalID(3)),
+                    Operation::Resolve(Box::new([SignalID(4), SignalID(5)])),
+                ]),
+                16,
+            );
+            assert!(matches!(
+                circuit.run_until_stable(16),
+                RunOutcome::Reached(_)
+            ));
+            circuit.inspect().1[6]
+        };
+
+        assert_eq!(bus(Signal::False, Signal::False), Signal::HighImpedance);
+        assert_eq!(bus(Signal::True, Signal::False), Signal::True);
+        assert_eq!(bus(Signal::False, Signal::True), Signal::False);
+        assert_eq!(bus(Signal::True, Signal::True), Signal::Undefined);
+        assert_eq!(bus(Signal::Undefined, Signal::False), Signal::Undefined);
+        assert_eq!(
+            bus(Signal::UncontrolledTrue, Signal::True),
+            Signal::UncontrolledTrue
+        );
+
+        // resolving is order independent so it doesn't matter how drivers are listed
+        let signals = [
+            Signal::False,
+            Signal::True,
+            Signal::UncontrolledFalse,
+            Signal::UncontrolledTrue,
+            Signal::HighImpedance,
+            Signal::Undefined,
+        ];
+        for a in signals {
+            assert_eq!(a.resolve(Signal::HighImpedance), a);
+            for b in signals {
+                assert_eq!(a.resolve(b), b.resolve(a));
+                for c in signals {
+                    assert_eq!(a.resolve(b).resolve(c), a.resolve(b.resolve(c)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn variadic_gates_and_mux() {
+        use std::sync::Arc;
+
+        let signals = [
+            Signal::False,
+            Signal::True,
+            Signal::UncontrolledFalse,
+            Signal::UncontrolledTrue,
+            Signal::HighImpedance,
+            Signal::Undefined,
+        ];
+        let constant = |signal: Signal| {
+            Operation::Input(InputHandler::new(Arc::new(move |_index, _tick| signal)))
+        };
+
+        for a in signals {
+            for b in signals {
+                for c in signals {
+                    let inputs: Box<[SignalID]> = Box::new([SignalID(0), SignalID(1), SignalID(2)]);
+                    let mut circuit = Circuit::new(
+                        Box::new([
+                            constant(a),
+                            constant(b),
+                            constant(c),
+                            Operation::AndN(inputs.clone()),
+                            Operation::NandN(inputs.clone()),
+                            Operation::OrN(inputs.clone()),
+                            Operation::NorN(inputs.clone()),
+                            Operation::XorN(inputs.clone()),
+                            Operation::XnorN(inputs),
+                            Operation::Mux(SignalID(0), SignalID(1), SignalID(2)),
+                        ]),
+                        16,
+                    );
+
+                    // every gate settles one tick after its inputs no matter how wide
+                    circuit.run_for(2);
+                    let state = circuit.inspect().1;
+                    assert_eq!(state[3], a & b & c);
+                    assert_eq!(state[4], !(a & b & c));
+                    assert_eq!(state[5], a | b | c);
+                    assert_eq!(state[6], !(a | b | c));
+                    assert_eq!(state[7], a ^ b ^ c);
+                    assert_eq!(state[8], !(a ^ b ^ c));
+                    assert_eq!(state[9], (b & !a) | (c & a));
+                }
+            }
+        }
+
+        // an empty gate produces its identity
+        let mut circuit = Circuit::new(
+            Box::new([Operation::AndN(Box::new([])), Operation::OrN(Box::new([]))]),
+            16,
+        );
+        circuit.tick();
+        assert_eq!(circuit.inspect().1, [Signal::True, Signal::False]);
+    }
+
+    #[test]
+    fn lookup_tables() {
+        use super::builder::{BuilderError, GateLevelDescription};
+        use std::sync::Arc;
+
+        // without uncontrolled values a table agrees with the gate it encodes
+        let signals = [
+            Signal::False,
+            Signal::True,
+            Signal::HighImpedance,
+            Signal::Undefined,
+        ];
+        for a in signals {
+            for b in signals {
+                assert_eq!(Signal::lookup(0b1000, &[a, b]), a & b);
+                assert_eq!(Signal::lookup(0b1110, &[a, b]), a | b);
+                assert_eq!(Signal::lookup(0b0110, &[a, b]), a ^ b);
+            }
+        }
+
+        // a mux selecting input 1 or 2 with input 0
+        let mux = (0..8).fold(0u64, |table, index| {
+            let output = match index & 1 {
+                0 => index >> 1 & 1,
+                _ => index >> 2 & 1,
+            };
+            table | (output as u64) << index
+        });
+        let lookup = |inputs: [Signal; 3]| Signal::lookup(mux, &inputs);
+        assert_eq!(
+            lookup([Signal::Undefined, Signal::True, Signal::True]),
+            Signal::True
+        );
+        assert_eq!(
+            lookup([Signal::Undefined, Signal::True, Signal::False]),
+            Signal::Undefined
+        );
+        assert_eq!(
+            lookup([Signal::Undefined, Signal::HighImpedance, Signal::False]),
+            Signal::HighImpedance
+        );
+        assert_eq!(
+            lookup([Signal::UncontrolledTrue, Signal::False, Signal::True]),
+            Signal::UncontrolledTrue
+        );
+        assert_eq!(
+            lookup([Signal::UncontrolledTrue, Signal::True, Signal::True]),
+            Signal::True
+        );
+        assert_eq!(
+            lookup([Signal::False, Signal::UncontrolledFalse, Signal::Undefined]),
+            Signal::UncontrolledFalse
+        );
+
+        let mut gld = GateLevelDescription::new();
+        let inputs: Vec<SignalID> = (0..3).map(|_| gld.rz_alloc()).collect();
+        for (id, signal) in inputs
+            .iter()
+            .zip([Signal::True, Signal::False, Signal::True])
+        {
+            gld.mk_input(*id, Arc::new(move |_, _| signal)).unwrap();
+        }
+        let lut = gld.rz_alloc();
+        gld.mk_lut(lut, &inputs, mux).unwrap();
+        let too_wide = gld.rz_alloc();
+        assert_eq!(
+            gld.mk_lut(too_wide, &[inputs[0]; 7], 0),
+            Err(BuilderError::LutTooWide(too_wide))
+        );
+        gld.mk_not(too_wide, lut).unwrap();
+
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), 16);
+        circuit.run_for(2);
+        assert_eq!(circuit.inspect().1[lut.0], Signal::True);
+    }
+
+    #[test]
+    fn flip_flop_divides_clock() {
+        use super::builder::GateLevelDescription;
+        use std::sync::Arc;
+
+        const TPI: usize = 4;
+
+        // a flip-flop feeding back its own inverse toggles on every rising edge
+        let description = || {
+            let mut gld = GateLevelDescription::new();
+            let clk = gld.rz_alloc();
+            let reset = gld.rz_alloc();
+            let q = gld.rz_alloc();
+            let not_q = gld.rz_alloc();
+            gld.mk_input(
+                clk,
+                Arc::new(|_, tick| match tick % 2 {
+                    0 => Signal::False,
+                    _ => Signal::True,
+                }),
+            )
+            .unwrap();
+            gld.mk_input(
+                reset,
+                Arc::new(|_, tick| match tick == 0 {
+                    true => Signal::True,
+                    false => Signal::False,
+                }),
+            )
+            .unwrap();
+            gld.mk_dff(q, not_q, clk, Some(reset), None).unwrap();
+            gld.mk_not(not_q, q).unwrap();
+            (gld.into_desc().unwrap(), q)
+        };
+
+        let (desc, q) = description();
+        let mut full_sweep = Circuit::new(desc, TPI);
+        let mut event_driven = Circuit::new(description().0, TPI).with_kernel(Kernel::EventDriven);
+        let mut bit_sliced = Circuit::new(description().0, TPI).with_kernel(Kernel::BitSliced);
+
+        let mut samples = Vec::new();
+        for tick in 0..16 * TPI {
+            full_sweep.tick();
+            event_driven.tick();
+            bit_sliced.tick();
+            assert_eq!(full_sweep.inspect().1, event_driven.inspect().1);
+            assert_eq!(full_sweep.inspect().1, bit_sliced.inspect().1);
+
+            // once per clock period, after the flip-flop has settled
+            if tick % (2 * TPI) == 2 * TPI - 1 {
+                samples.push(full_sweep.inspect().1[q.0]);
+            }
+        }
+
+        let expected: Vec<Signal> = (0..samples.len())
+            .map(|period| match period % 2 {
+                0 => Signal::True,
+                _ => Signal::False,
+            })
+            .collect();
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn ram_and_rom() {
+        use super::builder::{BuilderError, GateLevelDescription};
+        use super::operation::ReadMode;
+        use std::sync::Arc;
+        use Signal::*;
+
+        const TPI: usize = 4;
+
+        fn level(value: bool) -> Signal {
+            match value {
+                true => True,
+                false => False,
+            }
+        }
+
+        // one address bit and two data bits, every pair of samples is one clock period
+        // writing: word 0 <- 01, word 1 <- 10, nothing, then 11 to an unknown address
+        let mut gld = GateLevelDescription::new();
+        let clk = gld.rz_alloc();
+        let address = gld.rz_alloc();
+        let write_enable = gld.rz_alloc();
+        let data_in = [gld.rz_alloc(), gld.rz_alloc()];
+        let ram = gld.rz_alloc();
+        let data_out = [gld.rz_alloc(), gld.rz_alloc()];
+        let rom = gld.rz_alloc();
+        let rom_out = [gld.rz_alloc(), gld.rz_alloc()];
+        gld.mk_input(clk, Arc::new(|_, sample| level(sample % 2 == 1)))
+            .unwrap();
+        gld.mk_input(
+            address,
+            Arc::new(|_, sample| match sample / 2 {
+                1 => True,
+                3.. => Undefined,
+                _ => False,
+            }),
+        )
+        .unwrap();
+        gld.mk_input(write_enable, Arc::new(|_, sample| level(sample / 2 != 2)))
+            .unwrap();
+        gld.mk_input(
+            data_in[0],
+            Arc::new(|_, sample| level(sample / 2 == 0 || sample / 2 == 3)),
+        )
+        .unwrap();
+        gld.mk_input(
+            data_in[1],
+            Arc::new(|_, sample| level(sample / 2 == 1 || sample / 2 == 3)),
+        )
+        .unwrap();
+        gld.mk_ram(
+            ram,
+            &data_out,
+            &[address],
+            &data_in,
+            write_enable,
+            clk,
+            ReadMode::Asynchronous,
+        )
+        .unwrap();
+        gld.mk_rom(rom, &rom_out, &[address], Some(clk)).unwrap();
+        let mut wrong = GateLevelDescription::new();
+        let (loc, out, input) = (wrong.rz_alloc(), wrong.rz_alloc(), wrong.rz_alloc());
+        assert!(matches!(
+            wrong.mk_ram(
+                loc,
+                &[out],
+                &[],
+                &[input, input],
+                input,
+                input,
+                ReadMode::Asynchronous
+            ),
+            Err(BuilderError::MemoryWidth(_))
+        ));
+        let mut circuit = Circuit::new(gld.into_desc().unwrap(), TPI).with_history(64);
+        assert_eq!(circuit.inspect_memory(ram).unwrap(), &[Undefined; 4]);
+        assert_eq!(
+            circuit.inspect_memory(clk),
+            Err(MemoryError::NotAMemory(clk))
+        );
+        circuit.load_memory_words(rom, &[0b10, 0b01]).unwrap();
+        assert_eq!(
+            circuit.inspect_memory(rom).unwrap(),
+            &[False, True, True, False]
+        );
+        assert_eq!(
+            circuit.load_memory(rom, &[True; 3]),
+            Err(MemoryError::SizeMismatch {
+                expected: 4,
+                found: 3
+            })
+        );
+
+        let mut read = Vec::new();
+        let mut rom_read = Vec::new();
+        for tick in 0..6 * TPI {
+            circuit.tick();
+            if tick % TPI == TPI - 1 {
+                let signals = circuit.inspect().1;
+                read.push([signals[data_out[0].0], signals[data_out[1].0]]);
+                rom_read.push([signals[rom_out[0].0], signals[rom_out[1].0]]);
+            }
+        }
+        assert_eq!(
+            circuit.inspect_memory(ram).unwrap(),
+            &[True, False, False, True]
+        );
+
+        // asynchronous reads follow the address straight away, so each write shows
+        // up in the sample it happens in
+        assert_eq!(
+            read,
+            [
+                [Undefined, Undefined],
+                [True, False],
+                [Undefined, Undefined],
+                [False, True],
+                [True, False],
+                [True, False],
+            ]
+        );
+        // synchronous reads only update on a rising edge
+        assert_eq!(rom_read[1], [False, True]);
+        assert_eq!(rom_read[2], [False, True]);
+        assert_eq!(rom_read[3], [True, False]);
+        assert_eq!(rom_read[5], [False, True]);
+
+        // writing to an unknown address spoils every bit that would have changed
+        let snapshot = circuit.snapshot();
+        for _ in 0..2 * TPI {
+            circuit.tick();
+        }
+        assert_eq!(
+            circuit.inspect_memory(ram).unwrap(),
+            &[True, Undefined, Undefined, True]
+        );
+
+        // and is undone by stepping back or restoring
+        for _ in 0..2 * TPI {
+            circuit.step_back().unwrap();
+        }
+        assert_eq!(
+            circuit.inspect_memory(ram).unwrap(),
+            &[True, False, False, True]
+        );
+        for _ in 0..2 * TPI {
+            circuit.tick();
+        }
+        circuit.restore(&snapshot).unwrap();
+        assert_eq!(
+            circuit.inspect_memory(ram).unwrap(),
+            &[True, False, False, True]
+        );
+    }
+
+    #[test]
+    fn force_and_release() {
+        use super::builder::GateLevelDescription;
+        use std::sync::Arc;
+
+        const TPI: usize = 4;
+
+        // an input followed by two inverters, the middle one gets forced
+        let description = || {
+            let mut gld = GateLevelDescription::new();
+            let a = gld.rz_alloc();
+            let b = gld.rz_alloc();
+            let c = gld.rz_alloc();
+            gld.mk_input(a, Arc::new(|_, _| Signal::True)).unwrap();
+            gld.mk_not(b, a).unwrap();
+            gld.mk_not(c, b).unwrap();
+            (gld.into_desc().unwrap(), b, c)
+        };
+
+        let (desc, b, c) = description();
+        let names = HashMap::from([("top.b".to_string(), b)]);
+        let mut circuits = [
+            Circuit::new(desc, TPI).with_names(names),
+            Circuit::new(description().0, TPI).with_kernel(Kernel::EventDriven),
+            Circuit::new(description().0, TPI).with_kernel(Kernel::BitSliced),
+            Circuit::new(description().0, TPI).with_kernel(Kernel::Levelized),
+        ];
+
+        for (kernel, circuit) in circuits.iter_mut().enumerate() {
+            circuit.run_for(TPI as u128);
+            assert_eq!(circuit.inspect().1[b.0], Signal::False);
+            assert_eq!(circuit.inspect().1[c.0], Signal::True);
+
+            match kernel {
+                0 => circuit.force_named("top.b", Signal::True).unwrap(),
+                _ => circuit.force(b, Signal::True).unwrap(),
+            }
+            assert_eq!(circuit.forced().collect::<Vec<_>>(), [(b, Signal::True)]);
+            circuit.tick();
+            assert_eq!(circuit.inspect().1[b.0], Signal::True);
+            circuit.tick();
+            assert_eq!(circuit.inspect().1[c.0], Signal::False);
+
+            // the force holds however long the circuit runs
+            circuit.run_for(2 * TPI as u128);
+            assert_eq!(circuit.inspect().1[b.0], Signal::True);
+            assert_eq!(circuit.inspect().1[c.0], Signal::False);
+
+            match kernel {
+                0 => circuit.release_named("top.b").unwrap(),
+                _ => circuit.release(b).unwrap(),
+            }
+            assert_eq!(circuit.forced().count(), 0);
+            circuit.run_for(2);
+            assert_eq!(circuit.inspect().1[b.0], Signal::False);
+            assert_eq!(circuit.inspect().1[c.0], Signal::True);
+        }
+
+        let circuit = &mut circuits[1];
+        assert_eq!(
+            circuit.force(SignalID(3), Signal::True),
+            Err(ForceError::OutOfRange(SignalID(3)))
+        );
+        assert_eq!(
+            circuit.force_named("top.b", Signal::True),
+            Err(ForceError::UnknownName("top.b".to_string()))
+        );
+        assert_eq!(circuits[0].lookup("top.b"), Some(b));
+    }
+
+    #[test]
+    fn ordered_outputs() {
+        use super::builder::GateLevelDescription;
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        // plenty of outputs so the kernel spreads them over several threads
+        let mut gld = GateLevelDescription::new();
+        let a = gld.rz_alloc();
+        gld.mk_input(
+            a,
+            Arc::new(|_, sample| match sample % 2 {
+                0 => Signal::True,
+                _ => Signal::False,
+            }),
+        )
+        .unwrap();
+        for _ in 0..64 {
+            let out = gld.rz_alloc();
+            let sink = events.clone();
+            gld.mk_lane_output(
+                out,
+                a,
+                Arc::new(move |index, tick, lane, value| {
+                    sink.lock().unwrap().push((tick, index, lane, value))
+                }),
+            )
+            .unwrap();
+        }
+        let description = gld.into_desc().unwrap();
+
+        let mut unordered = Circuit::new(description.clone(), 2).with_lanes(2);
+        unordered.run_for(8);
+        let mut expected = std::mem::take(&mut *events.lock().unwrap());
+        expected.sort_by_key(|(tick, index, lane, _)| (*tick, *index, *lane));
+
+        let mut ordered = Circuit::new(description, 2)
+            .with_lanes(2)
+            .with_ordered_outputs();
+        ordered.run_for(8);
+        assert_eq!(*events.lock().unwrap(), expected);
+        assert_eq!(expected.len(), 8 * 64 * 2);
     }
 }
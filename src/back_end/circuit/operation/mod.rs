@@ -1,7 +1,9 @@
 use std::fmt::{Debug, Display};
 use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug)]
+use super::stimulus::Stimulus;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SignalID(pub usize);
 
 pub enum Operation {
@@ -10,6 +12,15 @@ pub enum Operation {
         SignalID,
         OutputHandler<dyn Fn(usize, u128, super::Signal) + Sync + Send>,
     ),
+    /// An input which is sampled separately for each lane of a multi lane circuit.
+    LaneInput(LaneInputHandler<dyn Fn(usize, u128, usize) -> super::Signal + Sync + Send>),
+    /// An output which is told which lane of a multi lane circuit it is reporting.
+    LaneOutput(
+        SignalID,
+        LaneOutputHandler<dyn Fn(usize, u128, usize, super::Signal) + Sync + Send>,
+    ),
+    /// An input driven by a declarative stimulus instead of a closure.
+    Stimulus(Box<Stimulus>),
     Not(SignalID),
     And(SignalID, SignalID),
     Nand(SignalID, SignalID),
@@ -17,6 +28,228 @@ pub enum Operation {
     Nor(SignalID, SignalID),
     Xor(SignalID, SignalID),
     Xnor(SignalID, SignalID),
+    /// Variadic gates which reduce every listed signal in a single tick.
+    AndN(Box<[SignalID]>),
+    NandN(Box<[SignalID]>),
+    OrN(Box<[SignalID]>),
+    NorN(Box<[SignalID]>),
+    XorN(Box<[SignalID]>),
+    XnorN(Box<[SignalID]>),
+    /// Selects the second signal while the first (the select) is false and the
+    /// third while it is true.
+    Mux(SignalID, SignalID, SignalID),
+    /// A lookup table of up to 6 inputs, see `Signal::lookup` for the layout of the table.
+    Lut(Box<[SignalID]>, u64),
+    /// Drives the first signal while the second (the enable) is true, otherwise
+    /// releases the net with a high impedance output.
+    TriBuf(SignalID, SignalID),
+    /// A net shared by several drivers, see `Signal::resolve` for how they combine.
+    Resolve(Box<[SignalID]>),
+    /// An edge triggered flip-flop, see `FlipFlop`.
+    Dff(FlipFlop),
+    /// A level sensitive latch passing the first signal while the second (the
+    /// enable) is true and holding its value otherwise.
+    DLatch(SignalID, SignalID),
+    /// A block of memory, see `MemoryBlock`. The signal of the operation itself
+    /// carries no value, the data is read out with `MemRead`.
+    Memory(Box<MemoryBlock>),
+    /// One bit of the data output of the memory defined by the given operation.
+    MemRead(SignalID, usize),
+    /// Any other operation whose result takes longer than a single tick to appear.
+    Delayed(Box<Operation>, Delay),
+}
+
+/// The connections of a rising edge triggered D flip-flop.
+///
+/// Edges are found by comparing `clk` with `last_clk`, which must hold the value
+/// `clk` had on the previous tick. `GateLevelDescription::mk_dff` takes care of
+/// this by buffering the clock. The asynchronous reset takes priority over set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FlipFlop {
+    pub d: SignalID,
+    pub clk: SignalID,
+    pub last_clk: SignalID,
+    pub reset: Option<SignalID>,
+    pub set: Option<SignalID>,
+}
+
+/// The connections of a memory block with `2^address.len()` words of `width` bits.
+///
+/// Address and data bits are listed least significant first. Writes happen on the
+/// rising edge of the clock while `write_enable` is true, a block without a write
+/// enable is a ROM which can only be changed through `Circuit::load_memory`.
+/// Like `FlipFlop` the clock is given as the pair `(clk, last_clk)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MemoryBlock {
+    pub width: usize,
+    pub address: Box<[SignalID]>,
+    pub data_in: Box<[SignalID]>,
+    pub write_enable: Option<SignalID>,
+    pub clock: Option<(SignalID, SignalID)>,
+    pub read: ReadMode,
+}
+
+impl MemoryBlock {
+    /// The widest address `GateLevelDescription` accepts, storage for every word is
+    /// allocated up front so wider memories are rarely what was meant.
+    pub const MAX_ADDRESS_BITS: usize = 24;
+
+    pub fn words(&self) -> usize {
+        u32::try_from(self.address.len())
+            .ok()
+            .and_then(|bits| 1usize.checked_shl(bits))
+            .expect("memory address is wider than usize")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReadMode {
+    /// The data output follows the address like any other gate.
+    Asynchronous,
+    /// The data output only changes on the rising edge of the clock.
+    Synchronous,
+}
+
+/// How long the result of an operation takes to appear on its output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Delay {
+    /// Ticks between the inputs changing and the output following. Every operation
+    /// without a delay takes one tick.
+    pub ticks: usize,
+    pub kind: DelayKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DelayKind {
+    /// Every edge is reproduced on the output `ticks` later.
+    Transport,
+    /// Pulses shorter than `ticks` are swallowed, only values which are held for
+    /// at least the full delay make it to the output.
+    Inertial,
+}
+
+impl Delay {
+    pub fn transport(ticks: usize) -> Self {
+        assert!(ticks > 0, "operations take at least one tick");
+        Self {
+            ticks,
+            kind: DelayKind::Transport,
+        }
+    }
+
+    pub fn inertial(ticks: usize) -> Self {
+        assert!(ticks > 0, "operations take at least one tick");
+        Self {
+            ticks,
+            kind: DelayKind::Inertial,
+        }
+    }
+}
+
+impl Display for Delay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            DelayKind::Transport => write!(f, "transport delay {}", self.ticks),
+            DelayKind::Inertial => write!(f, "inertial delay {}", self.ticks),
+        }
+    }
+}
+
+impl Operation {
+    /// Returns the IDs of every signal this operation reads from.
+    pub fn inputs(&self) -> Vec<SignalID> {
+        match self {
+            Self::Input(_) | Self::LaneInput(_) | Self::Stimulus(_) => Vec::new(),
+            Self::Output(a, _) | Self::LaneOutput(a, _) | Self::Not(a) => vec![*a],
+            Self::And(a, b)
+            | Self::Nand(a, b)
+            | Self::Or(a, b)
+            | Self::Nor(a, b)
+            | Self::Xor(a, b)
+            | Self::Xnor(a, b)
+            | Self::TriBuf(a, b)
+            | Self::DLatch(a, b) => vec![*a, *b],
+            Self::AndN(inputs)
+            | Self::NandN(inputs)
+            | Self::OrN(inputs)
+            | Self::NorN(inputs)
+            | Self::XorN(inputs)
+            | Self::XnorN(inputs)
+            | Self::Lut(inputs, _)
+            | Self::Resolve(inputs) => inputs.to_vec(),
+            Self::Mux(select, a, b) => vec![*select, *a, *b],
+            Self::Memory(block) => block
+                .address
+                .iter()
+                .chain(block.data_in.iter())
+                .copied()
+                .chain(block.write_enable)
+                .chain(block.clock.into_iter().flat_map(|(clk, last)| [clk, last]))
+                .collect(),
+            Self::MemRead(memory, _) => vec![*memory],
+            Self::Dff(ff) => [
+                Some(ff.d),
+                Some(ff.clk),
+                Some(ff.last_clk),
+                ff.reset,
+                ff.set,
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            Self::Delayed(op, _) => op.inputs(),
+        }
+    }
+
+    /// Copies the operation with every signal it reads passed through `f`. I/O
+    /// handlers are shared with the original.
+    pub fn map_inputs(&self, f: &impl Fn(SignalID) -> SignalID) -> Self {
+        let list = |ids: &[SignalID]| ids.iter().map(|id| f(*id)).collect();
+        match self {
+            Self::Input(_) | Self::LaneInput(_) | Self::Stimulus(_) => self.clone(),
+            Self::Output(a, OutputHandler { handler }) => {
+                Self::Output(f(*a), OutputHandler::new(handler.clone()))
+            }
+            Self::LaneOutput(a, LaneOutputHandler { handler }) => {
+                Self::LaneOutput(f(*a), LaneOutputHandler::new(handler.clone()))
+            }
+            Self::Not(a) => Self::Not(f(*a)),
+            Self::And(a, b) => Self::And(f(*a), f(*b)),
+            Self::Nand(a, b) => Self::Nand(f(*a), f(*b)),
+            Self::Or(a, b) => Self::Or(f(*a), f(*b)),
+            Self::Nor(a, b) => Self::Nor(f(*a), f(*b)),
+            Self::Xor(a, b) => Self::Xor(f(*a), f(*b)),
+            Self::Xnor(a, b) => Self::Xnor(f(*a), f(*b)),
+            Self::AndN(inputs) => Self::AndN(list(inputs)),
+            Self::NandN(inputs) => Self::NandN(list(inputs)),
+            Self::OrN(inputs) => Self::OrN(list(inputs)),
+            Self::NorN(inputs) => Self::NorN(list(inputs)),
+            Self::XorN(inputs) => Self::XorN(list(inputs)),
+            Self::XnorN(inputs) => Self::XnorN(list(inputs)),
+            Self::Mux(select, a, b) => Self::Mux(f(*select), f(*a), f(*b)),
+            Self::Lut(inputs, table) => Self::Lut(list(inputs), *table),
+            Self::TriBuf(data, enable) => Self::TriBuf(f(*data), f(*enable)),
+            Self::Resolve(drivers) => Self::Resolve(list(drivers)),
+            Self::Dff(ff) => Self::Dff(FlipFlop {
+                d: f(ff.d),
+                clk: f(ff.clk),
+                last_clk: f(ff.last_clk),
+                reset: ff.reset.map(f),
+                set: ff.set.map(f),
+            }),
+            Self::Memory(block) => Self::Memory(Box::new(MemoryBlock {
+                width: block.width,
+                address: list(&block.address),
+                data_in: list(&block.data_in),
+                write_enable: block.write_enable.map(f),
+                clock: block.clock.map(|(clk, last)| (f(clk), f(last))),
+                read: block.read,
+            })),
+            Self::MemRead(memory, bit) => Self::MemRead(f(*memory), *bit),
+            Self::DLatch(d, enable) => Self::DLatch(f(*d), f(*enable)),
+            Self::Delayed(op, delay) => Self::Delayed(Box::new(op.map_inputs(f)), *delay),
+        }
+    }
 }
 
 impl Clone for Operation {
@@ -28,6 +261,13 @@ impl Clone for Operation {
             Self::Output(var, OutputHandler { handler }) => {
                 Self::Output(var.clone(), OutputHandler::new(handler.clone()))
             }
+            Self::LaneInput(LaneInputHandler { handler }) => {
+                Self::LaneInput(LaneInputHandler::new(handler.clone()))
+            }
+            Self::Stimulus(stimulus) => Self::Stimulus(stimulus.clone()),
+            Self::LaneOutput(var, LaneOutputHandler { handler }) => {
+                Self::LaneOutput(*var, LaneOutputHandler::new(handler.clone()))
+            }
             Self::Not(var) => Self::Not(var.clone()),
             Self::And(lhs, rhs) => Self::And(lhs.clone(), rhs.clone()),
             Self::Nand(lhs, rhs) => Self::Nand(lhs.clone(), rhs.clone()),
@@ -35,6 +275,21 @@ impl Clone for Operation {
             Self::Nor(lhs, rhs) => Self::Nor(lhs.clone(), rhs.clone()),
             Self::Xor(lhs, rhs) => Self::Xor(lhs.clone(), rhs.clone()),
             Self::Xnor(lhs, rhs) => Self::Xnor(lhs.clone(), rhs.clone()),
+            Self::AndN(inputs) => Self::AndN(inputs.clone()),
+            Self::NandN(inputs) => Self::NandN(inputs.clone()),
+            Self::OrN(inputs) => Self::OrN(inputs.clone()),
+            Self::NorN(inputs) => Self::NorN(inputs.clone()),
+            Self::XorN(inputs) => Self::XorN(inputs.clone()),
+            Self::XnorN(inputs) => Self::XnorN(inputs.clone()),
+            Self::Mux(select, a, b) => Self::Mux(*select, *a, *b),
+            Self::Lut(inputs, table) => Self::Lut(inputs.clone(), *table),
+            Self::TriBuf(data, enable) => Self::TriBuf(*data, *enable),
+            Self::Resolve(drivers) => Self::Resolve(drivers.clone()),
+            Self::Dff(ff) => Self::Dff(*ff),
+            Self::Memory(block) => Self::Memory(block.clone()),
+            Self::MemRead(memory, bit) => Self::MemRead(*memory, *bit),
+            Self::DLatch(d, enable) => Self::DLatch(*d, *enable),
+            Self::Delayed(op, delay) => Self::Delayed(op.clone(), *delay),
         }
     }
 }
@@ -47,6 +302,9 @@ impl Debug for Operation {
             match self {
                 Self::Input(_) => format!("input",),
                 Self::Output(i, _) => format!("output Of: {}", i.0),
+                Self::LaneInput(_) => format!("lane input",),
+                Self::LaneOutput(i, _) => format!("lane output Of: {}", i.0),
+                Self::Stimulus(stimulus) => format!("stimulus {stimulus}"),
                 Self::Not(i) => format!("not Of: {}", i.0),
                 Self::And(i, j) => format!("and Of: {}, {}", i.0, j.0),
                 Self::Nand(i, j) => format!("nand Of: {}, {}", i.0, j.0),
@@ -54,6 +312,38 @@ impl Debug for Operation {
                 Self::Nor(i, j) => format!("nor Of: {}, {}", i.0, j.0),
                 Self::Xor(i, j) => format!("xor Of: {}, {}", i.0, j.0),
                 Self::Xnor(i, j) => format!("xnor Of: {}, {}", i.0, j.0),
+                Self::AndN(inputs) => format!("and Of: {}", list(inputs)),
+                Self::NandN(inputs) => format!("nand Of: {}", list(inputs)),
+                Self::OrN(inputs) => format!("or Of: {}", list(inputs)),
+                Self::NorN(inputs) => format!("nor Of: {}", list(inputs)),
+                Self::XorN(inputs) => format!("xor Of: {}", list(inputs)),
+                Self::XnorN(inputs) => format!("xnor Of: {}", list(inputs)),
+                Self::Mux(s, i, j) => format!("mux Of: {}, {}, {}", s.0, i.0, j.0),
+                Self::Lut(inputs, table) => format!("lut {:#x} Of: {}", table, list(inputs)),
+                Self::TriBuf(i, j) => format!("tribuf Of: {}, {}", i.0, j.0),
+                Self::Resolve(drivers) => format!("resolve Of: {}", list(drivers)),
+                Self::Dff(ff) => format!(
+                    "dff Of: {}, {}{}{}",
+                    ff.d.0,
+                    ff.clk.0,
+                    match ff.reset {
+                        Some(i) => format!(" reset {}", i.0),
+                        None => String::new(),
+                    },
+                    match ff.set {
+                        Some(i) => format!(" set {}", i.0),
+                        None => String::new(),
+                    }
+                ),
+                Self::DLatch(i, j) => format!("latch Of: {}, {}", i.0, j.0),
+                Self::Memory(block) => format!(
+                    "memory {}x{} Of: {}",
+                    block.words(),
+                    block.width,
+                    list(&self.inputs())
+                ),
+                Self::MemRead(i, bit) => format!("memory read bit {} Of: {}", bit, i.0),
+                Self::Delayed(op, delay) => format!("{} of ({})", delay, op),
             }
         )
     }
@@ -67,6 +357,9 @@ impl Display for Operation {
             match self {
                 Self::Input(_) => format!("input",),
                 Self::Output(i, _) => format!("output Of: {}", i.0),
+                Self::LaneInput(_) => format!("lane input",),
+                Self::LaneOutput(i, _) => format!("lane output Of: {}", i.0),
+                Self::Stimulus(stimulus) => format!("stimulus {stimulus}"),
                 Self::Not(i) => format!("not Of: {}", i.0),
                 Self::And(i, j) => format!("and Of: {}, {}", i.0, j.0),
                 Self::Nand(i, j) => format!("nand Of: {}, {}", i.0, j.0),
@@ -74,11 +367,51 @@ impl Display for Operation {
                 Self::Nor(i, j) => format!("nor Of: {}, {}", i.0, j.0),
                 Self::Xor(i, j) => format!("xor Of: {}, {}", i.0, j.0),
                 Self::Xnor(i, j) => format!("xnor Of: {}, {}", i.0, j.0),
+                Self::AndN(inputs) => format!("and Of: {}", list(inputs)),
+                Self::NandN(inputs) => format!("nand Of: {}", list(inputs)),
+                Self::OrN(inputs) => format!("or Of: {}", list(inputs)),
+                Self::NorN(inputs) => format!("nor Of: {}", list(inputs)),
+                Self::XorN(inputs) => format!("xor Of: {}", list(inputs)),
+                Self::XnorN(inputs) => format!("xnor Of: {}", list(inputs)),
+                Self::Mux(s, i, j) => format!("mux Of: {}, {}, {}", s.0, i.0, j.0),
+                Self::Lut(inputs, table) => format!("lut {:#x} Of: {}", table, list(inputs)),
+                Self::TriBuf(i, j) => format!("tribuf Of: {}, {}", i.0, j.0),
+                Self::Resolve(drivers) => format!("resolve Of: {}", list(drivers)),
+                Self::Dff(ff) => format!(
+                    "dff Of: {}, {}{}{}",
+                    ff.d.0,
+                    ff.clk.0,
+                    match ff.reset {
+                        Some(i) => format!(" reset {}", i.0),
+                        None => String::new(),
+                    },
+                    match ff.set {
+                        Some(i) => format!(" set {}", i.0),
+                        None => String::new(),
+                    }
+                ),
+                Self::DLatch(i, j) => format!("latch Of: {}, {}", i.0, j.0),
+                Self::Memory(block) => format!(
+                    "memory {}x{} Of: {}",
+                    block.words(),
+                    block.width,
+                    list(&self.inputs())
+                ),
+                Self::MemRead(i, bit) => format!("memory read bit {} Of: {}", bit, i.0),
+                Self::Delayed(op, delay) => format!("{} of ({})", delay, op),
             }
         )
     }
 }
 
+/// Formats a list of IDs the same way the fixed arity operations are printed.
+fn list(ids: &[SignalID]) -> String {
+    ids.iter()
+        .map(|id| id.0.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[derive(Debug)]
 pub struct InputHandler<F>
 where
@@ -112,3 +445,37 @@ where
         Self { handler: func }
     }
 }
+
+#[derive(Debug)]
+pub struct LaneInputHandler<F>
+where
+    F: Fn(usize, u128, usize) -> super::Signal + Sync + Send + ?Sized,
+{
+    pub handler: Arc<F>,
+}
+
+impl<F> LaneInputHandler<F>
+where
+    F: Fn(usize, u128, usize) -> super::Signal + Sync + Send + ?Sized,
+{
+    pub fn new(func: Arc<F>) -> Self {
+        Self { handler: func }
+    }
+}
+
+#[derive(Debug)]
+pub struct LaneOutputHandler<F>
+where
+    F: Fn(usize, u128, usize, super::Signal) + Sync + Send + ?Sized,
+{
+    pub handler: Arc<F>,
+}
+
+impl<F> LaneOutputHandler<F>
+where
+    F: Fn(usize, u128, usize, super::Signal) + Sync + Send + ?Sized,
+{
+    pub fn new(func: Arc<F>) -> Self {
+        Self { handler: func }
+    }
+}
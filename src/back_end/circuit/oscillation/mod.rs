@@ -0,0 +1,181 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::operation::SignalID;
+use super::signal::Signal;
+
+/// A limit cycle found while the inputs of a circuit were held constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oscillation {
+    /// number of ticks before the state of the circuit repeats
+    pub period: u128,
+    /// the tick on which the repeat was detected
+    pub detected_at: u128,
+    /// every signal which changed value at some point during the cycle
+    pub toggling: Vec<SignalID>,
+}
+
+impl Display for Oscillation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "oscillation with a period of {} ticks detected at tick {} on signals {:?}",
+            self.period,
+            self.detected_at,
+            self.toggling.iter().map(|id| id.0).collect::<Vec<_>>()
+        )
+    }
+}
+
+/// Watches the state of a circuit for repeats.
+///
+/// Between input samples the next state of a circuit depends only on the current
+/// state, so once any state is seen twice within the same input window the circuit
+/// is stuck in a cycle until the inputs are sampled again. This means only cycles
+/// shorter than the ticks per input of the circuit can be detected.
+#[derive(Debug, Default)]
+pub struct OscillationDetector {
+    /// the tick the current input window began on
+    window_start: Option<u128>,
+    /// hash of every state seen in the current window and the tick it was seen on
+    seen: HashMap<u64, u128>,
+    /// the signals which changed on each tick of the current window along with the
+    /// value they changed from, enough to confirm a repeated hash is a repeated state
+    changes: Vec<Vec<(usize, Signal)>>,
+    /// set once a cycle was reported so each window is only reported once
+    reported: bool,
+    /// every oscillation reported since last taken
+    pub found: Vec<Oscillation>,
+    /// print a warning for each oscillation as it is found
+    pub warn: bool,
+}
+
+impl OscillationDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the current window, used when the state is modified outside of `tick`.
+    pub fn reset(&mut self) {
+        self.window_start = None;
+        self.seen.clear();
+        self.changes.clear();
+        self.reported = false;
+    }
+
+    /// Feeds the state after a tick to the detector. `sampled` is set when the tick
+    /// which was just run sampled the inputs and so started a new window.
+    pub fn observe(
+        &mut self,
+        tick: u128,
+        sampled: bool,
+        lanes: usize,
+        signals: &[Signal],
+        previous: &[Signal],
+    ) -> Option<Oscillation> {
+        let hash = hash(signals);
+
+        let window_start = match self.window_start {
+            Some(start) if !sampled => start,
+            _ => {
+                self.reset();
+                self.window_start = Some(tick);
+                self.seen.insert(hash, tick);
+                return None;
+            }
+        };
+
+        self.changes.push(
+            signals
+                .iter()
+                .zip(previous.iter())
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(|(index, (_, before))| (index, *before))
+                .collect(),
+        );
+
+        let first_seen = match self.seen.get(&hash) {
+            Some(t) => *t,
+            None => {
+                self.seen.insert(hash, tick);
+                return None;
+            }
+        };
+
+        // every signal changed since must be back where it started for the hash to
+        // be a real repeat rather than a collision
+        let since = (first_seen - window_start) as usize;
+        let mut started: HashMap<usize, Signal> = HashMap::new();
+        for (index, before) in self.changes[since..].iter().flatten() {
+            started.entry(*index).or_insert(*before);
+        }
+        if started
+            .iter()
+            .any(|(index, before)| signals[*index] != *before)
+        {
+            self.seen.insert(hash, tick);
+            return None;
+        }
+
+        // a period of one is a circuit which has settled
+        let period = tick - first_seen;
+        if period < 2 || self.reported {
+            return None;
+        }
+        self.reported = true;
+
+        let toggling: BTreeSet<usize> = started.keys().map(|index| index / lanes).collect();
+
+        let oscillation = Oscillation {
+            period,
+            detected_at: tick,
+            toggling: toggling.into_iter().map(SignalID).collect(),
+        };
+        self.found.push(oscillation.clone());
+        Some(oscillation)
+    }
+}
+
+fn hash(signals: &[Signal]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    signals.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Signal::*;
+
+    #[test]
+    fn hash_collisions_are_not_reported() {
+        let states = [[False, True], [True, True], [True, False], [False, True]];
+        let observe = |detector: &mut OscillationDetector, tick: usize| {
+            let previous = &states[tick.saturating_sub(1)];
+            detector.observe(tick as u128, tick == 0, 1, &states[tick], previous)
+        };
+
+        let mut detector = OscillationDetector::new();
+        for tick in 0..3 {
+            assert_eq!(observe(&mut detector, tick), None);
+        }
+        // pretend the last state hashed the same as the one seen on tick 1
+        detector.seen.insert(hash(&states[3]), 1);
+        assert_eq!(observe(&mut detector, 3), None);
+
+        let mut detector = OscillationDetector::new();
+        for tick in 0..3 {
+            assert_eq!(observe(&mut detector, tick), None);
+        }
+        assert_eq!(
+            observe(&mut detector, 3),
+            Some(Oscillation {
+                period: 3,
+                detected_at: 3,
+                toggling: vec![SignalID(0), SignalID(1)],
+            })
+        );
+    }
+}
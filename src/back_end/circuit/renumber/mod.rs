@@ -0,0 +1,262 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use super::graph;
+use super::operation::*;
+
+/// How to order operations when renumbering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Visits the circuit breadth first from its inputs, so the readers of a
+    /// signal tend to be numbered next to each other.
+    BreadthFirst,
+    /// Sorts operations by their distance from the inputs, with every feedback
+    /// loop sharing a level, so operations sit right after the ones they read.
+    Levelized,
+}
+
+/// Translates between the IDs of a description before and after renumbering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalMap {
+    /// the new ID of every old ID
+    forward: Box<[SignalID]>,
+    /// the old ID of every new ID
+    backward: Box<[SignalID]>,
+}
+
+impl SignalMap {
+    /// Gets the ID a signal was given by the renumbering.
+    pub fn new_id(&self, old: SignalID) -> SignalID {
+        self.forward[old.0]
+    }
+
+    /// Gets the ID a signal had before the renumbering.
+    pub fn old_id(&self, new: SignalID) -> SignalID {
+        self.backward[new.0]
+    }
+
+    /// Moves a table of net names over to the new IDs.
+    pub fn names(&self, names: &HashMap<String, SignalID>) -> HashMap<String, SignalID> {
+        names
+            .iter()
+            .map(|(name, id)| (name.clone(), self.new_id(*id)))
+            .collect()
+    }
+}
+
+/// Renumbers every operation of a description, rewriting the signals each one
+/// reads to match. In allocation order the reads of each tick land all over
+/// `signals` on large designs, grouping operations with the ones they read keeps
+/// them in cache.
+///
+/// Handlers still receive the ID the operation had originally so the renumbered
+/// circuit simulates identically, including its I/O.
+pub fn renumber(description: &[Operation], order: Order) -> (Box<[Operation]>, SignalMap) {
+    let backward: Box<[SignalID]> = match order {
+        Order::BreadthFirst => breadth_first(description),
+        Order::Levelized => levelized(description),
+    }
+    .into_iter()
+    .map(SignalID)
+    .collect();
+
+    let mut forward = vec![SignalID(0); description.len()].into_boxed_slice();
+    for (new, old) in backward.iter().enumerate() {
+        forward[old.0] = SignalID(new);
+    }
+
+    let map = SignalMap { forward, backward };
+    let description = map
+        .backward
+        .iter()
+        .map(|old| pin(old.0, description[old.0].map_inputs(&|id| map.new_id(id))))
+        .collect();
+    (description, map)
+}
+
+/// Makes the handlers of an operation see its old index whatever it is called with.
+fn pin(old: usize, op: Operation) -> Operation {
+    match op {
+        Operation::Input(InputHandler { handler }) => {
+            Operation::Input(InputHandler::new(Arc::new(move |_, sample| {
+                handler(old, sample)
+            })))
+        }
+        Operation::Output(a, OutputHandler { handler }) => Operation::Output(
+            a,
+            OutputHandler::new(Arc::new(move |_, tick, value| handler(old, tick, value))),
+        ),
+        Operation::LaneInput(LaneInputHandler { handler }) => {
+            Operation::LaneInput(LaneInputHandler::new(Arc::new(move |_, sample, lane| {
+                handler(old, sample, lane)
+            })))
+        }
+        Operation::LaneOutput(a, LaneOutputHandler { handler }) => Operation::LaneOutput(
+            a,
+            LaneOutputHandler::new(Arc::new(move |_, tick, lane, value| {
+                handler(old, tick, lane, value)
+            })),
+        ),
+        Operation::Delayed(inner, delay) => Operation::Delayed(Box::new(pin(old, *inner)), delay),
+        op => op,
+    }
+}
+
+fn breadth_first(description: &[Operation]) -> Vec<usize> {
+    let mut fanout = vec![Vec::new(); description.len()];
+    for (index, op) in description.iter().enumerate() {
+        for source in op.inputs() {
+            fanout[source.0].push(index);
+        }
+    }
+
+    let mut visited = vec![false; description.len()];
+    let mut order = Vec::with_capacity(description.len());
+    let mut queue: VecDeque<usize> = (0..description.len())
+        .filter(|index| description[*index].inputs().is_empty())
+        .collect();
+    queue.iter().for_each(|index| visited[*index] = true);
+
+    // anything not reachable from an operation without inputs is only driven by
+    // feedback, so the search restarts from the lowest index left over
+    let mut next_root = 0;
+    loop {
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for &reader in fanout[index].iter() {
+                if !visited[reader] {
+                    visited[reader] = true;
+                    queue.push_back(reader);
+                }
+            }
+        }
+        while next_root < description.len() && visited[next_root] {
+            next_root += 1;
+        }
+        if next_root == description.len() {
+            return order;
+        }
+        visited[next_root] = true;
+        queue.push_back(next_root);
+    }
+}
+
+fn levelized(description: &[Operation]) -> Vec<usize> {
+    let components = graph::components(description);
+    let mut component_of = vec![0; description.len()];
+    for (c, component) in components.iter().enumerate() {
+        for index in component {
+            component_of[*index] = c;
+        }
+    }
+
+    // components come in data flow order so every source has its level already
+    let mut levels = vec![0; components.len()];
+    for (c, component) in components.iter().enumerate() {
+        levels[c] = component
+            .iter()
+            .flat_map(|index| description[*index].inputs())
+            .map(|source| component_of[source.0])
+            .filter(|source| *source != c)
+            .map(|source| levels[source] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+
+    let mut order: Vec<usize> = (0..description.len()).collect();
+    order.sort_by_key(|index| (levels[component_of[*index]], *index));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::signal::Signal;
+    use crate::back_end::circuit::Circuit;
+    use std::sync::Mutex;
+
+    #[test]
+    fn renumbered_circuits_match() {
+        let outputs = Arc::new(Mutex::new(Vec::new()));
+
+        // a counter bit and a small memory, allocated back to front so that every
+        // operation reads signals after it
+        let mut gld = GateLevelDescription::new();
+        let ids: Vec<SignalID> = (0..9).map(|_| gld.rz_alloc()).collect();
+        let [out, read, ram, q, not_q, mixed, reset, enable, clk] = ids[..] else {
+            unreachable!()
+        };
+        let input = |bit: usize| {
+            Arc::new(move |index: usize, sample: u128| {
+                match (sample >> bit) & 1 == 1 || (index == 6 && sample == 0) {
+                    true => Signal::True,
+                    false => Signal::False,
+                }
+            })
+        };
+        gld.mk_input(clk, input(0)).unwrap();
+        gld.mk_input(enable, input(2)).unwrap();
+        gld.mk_input(reset, input(8)).unwrap();
+        gld.mk_dff(q, not_q, clk, Some(reset), None).unwrap();
+        gld.mk_not(not_q, q).unwrap();
+        gld.mk_lut(mixed, &[q, enable, clk], 0b1001_0110).unwrap();
+        gld.mk_ram(
+            ram,
+            &[read],
+            &[q],
+            &[mixed],
+            enable,
+            clk,
+            ReadMode::Asynchronous,
+        )
+        .unwrap();
+        let sink = outputs.clone();
+        gld.mk_output(
+            out,
+            read,
+            Arc::new(move |index, tick, value| sink.lock().unwrap().push((index, tick, value))),
+        )
+        .unwrap();
+        let description = gld.into_desc().unwrap();
+
+        let run = |description: Box<[Operation]>| {
+            let mut circuit = Circuit::new(description, 4);
+            circuit.run_for(64);
+            let trace = std::mem::take(&mut *outputs.lock().unwrap());
+            (circuit.inspect().1.to_vec(), trace)
+        };
+        let (signals, trace) = run(description.clone());
+        assert!(trace
+            .iter()
+            .any(|(_, _, value)| *value != Signal::Undefined));
+
+        for order in [Order::BreadthFirst, Order::Levelized] {
+            let (renumbered, map) = renumber(&description, order);
+            assert_ne!(map.new_id(clk), clk);
+            for (old, op) in description.iter().enumerate() {
+                let new = map.new_id(SignalID(old));
+                assert_eq!(map.old_id(new), SignalID(old));
+                let inputs: Vec<SignalID> =
+                    op.inputs().into_iter().map(|id| map.new_id(id)).collect();
+                assert_eq!(renumbered[new.0].inputs(), inputs);
+            }
+
+            let (renumbered_signals, renumbered_trace) = run(renumbered);
+            let translated: Vec<Signal> = (0..signals.len())
+                .map(|old| renumbered_signals[map.new_id(SignalID(old)).0])
+                .collect();
+            assert_eq!(translated, signals);
+            assert_eq!(renumbered_trace, trace);
+
+            let names = HashMap::from([("clk".to_string(), clk)]);
+            assert_eq!(map.names(&names)["clk"], map.new_id(clk));
+        }
+
+        // levelized puts every input first
+        let (_, map) = renumber(&description, Order::Levelized);
+        let mut first = [clk, enable, reset].map(|id| map.new_id(id).0);
+        first.sort();
+        assert_eq!(first, [0, 1, 2]);
+    }
+}
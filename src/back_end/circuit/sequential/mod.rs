@@ -0,0 +1,173 @@
+use super::signal::Signal;
+
+/// The logic level of a signal, `None` when it could be either.
+pub fn level(signal: Signal) -> Option<bool> {
+    match signal {
+        Signal::False | Signal::UncontrolledFalse => Some(false),
+        Signal::True | Signal::UncontrolledTrue => Some(true),
+        Signal::HighImpedance | Signal::Undefined => None,
+    }
+}
+
+fn uncontrolled(signal: Signal) -> Signal {
+    match signal {
+        Signal::False => Signal::UncontrolledFalse,
+        Signal::True => Signal::UncontrolledTrue,
+        _ => signal,
+    }
+}
+
+/// The value a storage element captures, nothing holds on to high impedance.
+pub fn capture(d: Signal) -> Signal {
+    match d {
+        Signal::HighImpedance => Signal::Undefined,
+        _ => d,
+    }
+}
+
+/// Used when it's unknown whether the element loads `d` or holds `current`, the
+/// value is only kept if both agree.
+pub fn either(current: Signal, d: Signal) -> Signal {
+    match level(current).is_some() && level(current) == level(d) {
+        true => current,
+        false => Signal::Undefined,
+    }
+}
+
+/// Whether a clock rose between the previous tick and this one, `None` when the
+/// clock is unknown and could have.
+pub fn rising(clk: Signal, last_clk: Signal) -> Option<bool> {
+    match (level(last_clk), level(clk)) {
+        (Some(false), Some(true)) => Some(true),
+        (Some(true), _) | (_, Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+/// Applies an asynchronous control which forces the output to `value` while asserted.
+fn force(next: Signal, control: Option<Signal>, value: Signal) -> Signal {
+    match control {
+        None | Some(Signal::False) | Some(Signal::UncontrolledFalse) => next,
+        Some(Signal::True) => value,
+        Some(Signal::UncontrolledTrue) => uncontrolled(value),
+        Some(Signal::HighImpedance) | Some(Signal::Undefined) => either(next, value),
+    }
+}
+
+/// The next value of a rising edge triggered flip-flop.
+///
+/// A rising edge is a clock which was low on the previous tick and is high now.
+/// Uncontrolled clock values count at their level, inputs inject them on every edge
+/// so tainting the captured value would leave every clocked design uncontrolled.
+/// If the clock is unknown and could have risen the output only survives if `d`
+/// matches it.
+pub fn flip_flop(
+    current: Signal,
+    d: Signal,
+    clk: Signal,
+    last_clk: Signal,
+    reset: Option<Signal>,
+    set: Option<Signal>,
+) -> Signal {
+    let next = match rising(clk, last_clk) {
+        Some(true) => capture(d),
+        Some(false) => current,
+        None => either(current, d),
+    };
+
+    // reset is applied last so it wins over set
+    force(force(next, set, Signal::True), reset, Signal::False)
+}
+
+/// The next value of a level sensitive latch, transparent while `enable` is high.
+pub fn latch(current: Signal, d: Signal, enable: Signal) -> Signal {
+    match enable {
+        Signal::True => capture(d),
+        Signal::UncontrolledTrue => uncontrolled(capture(d)),
+        Signal::False | Signal::UncontrolledFalse => current,
+        Signal::HighImpedance | Signal::Undefined => either(current, d),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flip_flop_edges() {
+        use Signal::*;
+
+        // only a rising edge loads d
+        assert_eq!(flip_flop(False, True, True, False, None, None), True);
+        assert_eq!(flip_flop(False, True, True, True, None, None), False);
+        assert_eq!(flip_flop(False, True, False, True, None, None), False);
+        assert_eq!(flip_flop(False, True, False, False, None, None), False);
+
+        // uncontrolled clocks count at their level, uncontrolled data is kept
+        assert_eq!(
+            flip_flop(False, True, UncontrolledTrue, False, None, None),
+            True
+        );
+        assert_eq!(
+            flip_flop(False, True, UncontrolledTrue, True, None, None),
+            False
+        );
+        assert_eq!(
+            flip_flop(True, UncontrolledFalse, True, UncontrolledFalse, None, None),
+            UncontrolledFalse
+        );
+
+        // an unknown clock only matters if d differs from the output
+        assert_eq!(flip_flop(True, True, Undefined, False, None, None), True);
+        assert_eq!(
+            flip_flop(False, True, Undefined, False, None, None),
+            Undefined
+        );
+        assert_eq!(
+            flip_flop(False, True, True, HighImpedance, None, None),
+            Undefined
+        );
+        assert_eq!(flip_flop(False, True, Undefined, True, None, None), False);
+
+        // high impedance isn't stored
+        assert_eq!(
+            flip_flop(False, HighImpedance, True, False, None, None),
+            Undefined
+        );
+
+        // asynchronous controls, reset wins
+        assert_eq!(flip_flop(True, True, True, False, Some(True), None), False);
+        assert_eq!(
+            flip_flop(False, False, False, False, None, Some(True)),
+            True
+        );
+        assert_eq!(
+            flip_flop(True, True, False, False, Some(True), Some(True)),
+            False
+        );
+        assert_eq!(
+            flip_flop(True, True, False, False, Some(UncontrolledTrue), None),
+            UncontrolledFalse
+        );
+        assert_eq!(
+            flip_flop(False, True, False, False, Some(Undefined), None),
+            False
+        );
+        assert_eq!(
+            flip_flop(True, True, False, False, Some(Undefined), None),
+            Undefined
+        );
+    }
+
+    #[test]
+    fn latch_levels() {
+        use Signal::*;
+
+        assert_eq!(latch(False, True, True), True);
+        assert_eq!(latch(False, True, False), False);
+        assert_eq!(latch(False, True, UncontrolledTrue), UncontrolledTrue);
+        assert_eq!(latch(True, False, UncontrolledFalse), True);
+        assert_eq!(latch(True, True, Undefined), True);
+        assert_eq!(latch(True, False, Undefined), Undefined);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod packed;
+
 /// different signal states produced by a gate or circuit
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum Signal {
     False,
     True,
@@ -9,6 +11,184 @@ pub enum Signal {
     Undefined,
 }
 
+impl Signal {
+    /// Combines two drivers of the same net.
+    ///
+    /// High impedance yields to any driven value and drivers which disagree produce
+    /// `Undefined`. Uncontrolled values stay dominant so an uncontrolled loop is still
+    /// visible through a shared net, when two uncontrolled drivers disagree the false
+    /// value wins just like for `&`.
+    pub fn resolve(self, other: Self) -> Self {
+        match (self, other) {
+            (Signal::False, Signal::False) => Signal::False,
+            (Signal::False, Signal::True) => Signal::Undefined,
+            (Signal::False, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::False, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::False, Signal::HighImpedance) => Signal::False,
+            (Signal::False, Signal::Undefined) => Signal::Undefined,
+
+            (Signal::True, Signal::False) => Signal::Undefined,
+            (Signal::True, Signal::True) => Signal::True,
+            (Signal::True, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::True, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::True, Signal::HighImpedance) => Signal::True,
+            (Signal::True, Signal::Undefined) => Signal::Undefined,
+
+            (Signal::UncontrolledFalse, Signal::False) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::True) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::UncontrolledTrue) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::HighImpedance) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::Undefined) => Signal::UncontrolledFalse,
+
+            (Signal::UncontrolledTrue, Signal::False) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::True) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledTrue, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::HighImpedance) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::Undefined) => Signal::UncontrolledTrue,
+
+            (Signal::HighImpedance, Signal::False) => Signal::False,
+            (Signal::HighImpedance, Signal::True) => Signal::True,
+            (Signal::HighImpedance, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::HighImpedance, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::HighImpedance, Signal::HighImpedance) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::Undefined) => Signal::Undefined,
+
+            (Signal::Undefined, Signal::False) => Signal::Undefined,
+            (Signal::Undefined, Signal::True) => Signal::Undefined,
+            (Signal::Undefined, Signal::UncontrolledFalse) => Signal::UncontrolledFalse,
+            (Signal::Undefined, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::Undefined, Signal::HighImpedance) => Signal::Undefined,
+            (Signal::Undefined, Signal::Undefined) => Signal::Undefined,
+        }
+    }
+
+    /// The output of a tri-state buffer driving `self` while `enable` is applied.
+    ///
+    /// A disabled buffer releases the net, an unknown enable can't be relied on to
+    /// release it so the output is `Undefined` unless there was nothing to drive or
+    /// the value was already uncontrolled. An uncontrolled enable makes the driven
+    /// value uncontrolled.
+    pub fn tri_state(self, enable: Self) -> Self {
+        match (self, enable) {
+            (Signal::False, Signal::False) => Signal::HighImpedance,
+            (Signal::False, Signal::True) => Signal::False,
+            (Signal::False, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::False, Signal::UncontrolledTrue) => Signal::UncontrolledFalse,
+            (Signal::False, Signal::HighImpedance) => Signal::Undefined,
+            (Signal::False, Signal::Undefined) => Signal::Undefined,
+
+            (Signal::True, Signal::False) => Signal::HighImpedance,
+            (Signal::True, Signal::True) => Signal::True,
+            (Signal::True, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::True, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::True, Signal::HighImpedance) => Signal::Undefined,
+            (Signal::True, Signal::Undefined) => Signal::Undefined,
+
+            (Signal::UncontrolledFalse, Signal::False) => Signal::HighImpedance,
+            (Signal::UncontrolledFalse, Signal::True) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::UncontrolledFalse, Signal::UncontrolledTrue) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::HighImpedance) => Signal::UncontrolledFalse,
+            (Signal::UncontrolledFalse, Signal::Undefined) => Signal::UncontrolledFalse,
+
+            (Signal::UncontrolledTrue, Signal::False) => Signal::HighImpedance,
+            (Signal::UncontrolledTrue, Signal::True) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::UncontrolledTrue, Signal::UncontrolledTrue) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::HighImpedance) => Signal::UncontrolledTrue,
+            (Signal::UncontrolledTrue, Signal::Undefined) => Signal::UncontrolledTrue,
+
+            (Signal::HighImpedance, Signal::False) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::True) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::UncontrolledTrue) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::HighImpedance) => Signal::HighImpedance,
+            (Signal::HighImpedance, Signal::Undefined) => Signal::HighImpedance,
+
+            (Signal::Undefined, Signal::False) => Signal::HighImpedance,
+            (Signal::Undefined, Signal::True) => Signal::Undefined,
+            (Signal::Undefined, Signal::UncontrolledFalse) => Signal::HighImpedance,
+            (Signal::Undefined, Signal::UncontrolledTrue) => Signal::Undefined,
+            (Signal::Undefined, Signal::HighImpedance) => Signal::Undefined,
+            (Signal::Undefined, Signal::Undefined) => Signal::Undefined,
+        }
+    }
+
+    /// Looks up the output of a truth table. Bit `i` of `table` is the output when
+    /// input `k` is bit `k` of `i`, at most 6 inputs are supported.
+    ///
+    /// Undefined and high impedance inputs are tried both ways, when that changes the
+    /// output the result is unknown, high impedance if any of the inputs responsible
+    /// were high impedance and `Undefined` otherwise. A known result is uncontrolled
+    /// when flipping one of the uncontrolled inputs could change it.
+    pub fn lookup(table: u64, inputs: &[Signal]) -> Signal {
+        assert!(inputs.len() <= 6, "lookup tables have at most 6 inputs");
+
+        let mut value = 0;
+        let mut unknown = 0;
+        let mut impedance = 0;
+        let mut uncontrolled = 0;
+        for (k, input) in inputs.iter().enumerate() {
+            match input {
+                Signal::False => (),
+                Signal::True => value |= 1 << k,
+                Signal::UncontrolledFalse => uncontrolled |= 1 << k,
+                Signal::UncontrolledTrue => {
+                    value |= 1 << k;
+                    uncontrolled |= 1 << k;
+                }
+                Signal::HighImpedance => {
+                    unknown |= 1 << k;
+                    impedance |= 1 << k;
+                }
+                Signal::Undefined => unknown |= 1 << k,
+            }
+        }
+
+        let output = |index: usize| table >> index & 1 == 1;
+        // every assignment of the unknown inputs, including none
+        let assignments = || {
+            let mut subset = Some(0usize);
+            std::iter::from_fn(move || {
+                let current = subset?;
+                subset = match current == unknown {
+                    true => None,
+                    false => Some((current | !unknown).wrapping_add(1) & unknown),
+                };
+                Some(current)
+            })
+        };
+        let depends_on = |base: usize, bit: usize| {
+            assignments().any(|s| output(base | s) != output((base ^ bit) | s))
+        };
+
+        let first = output(value);
+        if assignments().any(|s| output(value | s) != first) {
+            let impedance_matters = (0..inputs.len())
+                .map(|k| 1 << k)
+                .filter(|bit| impedance & bit != 0)
+                .any(|bit| depends_on(value & !bit, bit));
+            return match impedance_matters {
+                true => Signal::HighImpedance,
+                false => Signal::Undefined,
+            };
+        }
+
+        let uncontrolled_matters = (0..inputs.len())
+            .map(|k| 1 << k)
+            .filter(|bit| uncontrolled & bit != 0)
+            .any(|bit| depends_on(value, bit));
+        match (first, uncontrolled_matters) {
+            (false, false) => Signal::False,
+            (true, false) => Signal::True,
+            (false, true) => Signal::UncontrolledFalse,
+            (true, true) => Signal::UncontrolledTrue,
+        }
+    }
+}
+
 impl std::ops::Not for Signal {
     type Output = Self;
     fn not(self) -> Signal {
@@ -0,0 +1,202 @@
+/*
+Bit sliced representation of Signal.
+
+Every signal is given a 3 bit code, and 64 signals are stored together as three u64 planes
+where lane i of the block is bit i of each plane. The codes are chosen so that AND is simply
+the maximum of the two operands when the codes are read as numbers:
+
+    True < UncontrolledTrue < Undefined < HighImpedance < False < UncontrolledFalse
+    000    001                010         011             100     101
+
+Not swaps True/False and UncontrolledTrue/UncontrolledFalse which in this encoding is
+flipping plane 2 wherever plane 1 is clear, and OR falls out of De Morgan's law. For XOR
+plane 1 marks the unknown values (Undefined and HighImpedance), and for known values
+plane 0 marks uncontrolled and plane 2 marks false, so the result can be built up directly.
+Codes 110 and 111 are never produced.
+*/
+
+use super::Signal;
+
+/// 64 signals packed into 3 bit planes so gates can be evaluated a word at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SignalBlock {
+    planes: [u64; 3],
+}
+
+impl SignalBlock {
+    pub const LANES: usize = 64;
+
+    /// Produces a block with every lane set to signal.
+    pub fn splat(signal: Signal) -> Self {
+        let code = encode(signal);
+        let mut planes = [0; 3];
+        for (bit, plane) in planes.iter_mut().enumerate() {
+            if code >> bit & 1 == 1 {
+                *plane = u64::MAX;
+            }
+        }
+        Self { planes }
+    }
+
+    pub fn get(&self, lane: usize) -> Signal {
+        let mut code = 0;
+        for (bit, plane) in self.planes.iter().enumerate() {
+            code |= ((plane >> lane & 1) as u8) << bit;
+        }
+        decode(code)
+    }
+
+    pub fn set(&mut self, lane: usize, signal: Signal) {
+        let code = encode(signal);
+        for (bit, plane) in self.planes.iter_mut().enumerate() {
+            *plane &= !(1 << lane);
+            *plane |= ((code >> bit & 1) as u64) << lane;
+        }
+    }
+
+    /// Takes the value of self in lanes set in mask and other everywhere else.
+    pub fn select(self, mask: u64, other: Self) -> Self {
+        let [a0, a1, a2] = self.planes;
+        let [b0, b1, b2] = other.planes;
+        Self {
+            planes: [
+                (a0 & mask) | (b0 & !mask),
+                (a1 & mask) | (b1 & !mask),
+                (a2 & mask) | (b2 & !mask),
+            ],
+        }
+    }
+}
+
+fn encode(signal: Signal) -> u8 {
+    match signal {
+        Signal::True => 0b000,
+        Signal::UncontrolledTrue => 0b001,
+        Signal::Undefined => 0b010,
+        Signal::HighImpedance => 0b011,
+        Signal::False => 0b100,
+        Signal::UncontrolledFalse => 0b101,
+    }
+}
+
+fn decode(code: u8) -> Signal {
+    match code {
+        0b000 => Signal::True,
+        0b001 => Signal::UncontrolledTrue,
+        0b010 => Signal::Undefined,
+        0b011 => Signal::HighImpedance,
+        0b100 => Signal::False,
+        0b101 => Signal::UncontrolledFalse,
+        _ => unreachable!("invalid packed signal code {code:#05b}"),
+    }
+}
+
+impl std::ops::Not for SignalBlock {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        let [p0, p1, p2] = self.planes;
+        Self {
+            planes: [p0, p1, p2 ^ !p1],
+        }
+    }
+}
+
+impl std::ops::BitAnd for SignalBlock {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let [a0, a1, a2] = self.planes;
+        let [b0, b1, b2] = rhs.planes;
+
+        // lanes where the code of self is greater than the code of rhs
+        let eq2 = !(a2 ^ b2);
+        let eq1 = !(a1 ^ b1);
+        let greater = (a2 & !b2) | (eq2 & a1 & !b1) | (eq2 & eq1 & a0 & !b0);
+
+        self.select(greater, rhs)
+    }
+}
+
+impl std::ops::BitOr for SignalBlock {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        !(!self & !rhs)
+    }
+}
+
+impl std::ops::BitXor for SignalBlock {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let [a0, a1, a2] = self.planes;
+        let [b0, b1, b2] = rhs.planes;
+
+        let unknown = a1 | b1;
+        let high_impedance = (a1 & a0) | (b1 & b0);
+
+        Self {
+            planes: [
+                high_impedance | (!unknown & (a0 | b0)),
+                unknown,
+                !unknown & !(a2 ^ b2),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNALS: [Signal; 6] = [
+        Signal::False,
+        Signal::True,
+        Signal::UncontrolledFalse,
+        Signal::UncontrolledTrue,
+        Signal::HighImpedance,
+        Signal::Undefined,
+    ];
+
+    /// Packs every pair of signals into lanes of two blocks so a single
+    /// operation on the blocks covers the whole truth table.
+    fn all_pairs() -> (SignalBlock, SignalBlock) {
+        let mut lhs = SignalBlock::default();
+        let mut rhs = SignalBlock::default();
+        for (i, a) in SIGNALS.iter().enumerate() {
+            for (j, b) in SIGNALS.iter().enumerate() {
+                lhs.set(i * SIGNALS.len() + j, *a);
+                rhs.set(i * SIGNALS.len() + j, *b);
+            }
+        }
+        (lhs, rhs)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut block = SignalBlock::splat(Signal::UncontrolledFalse);
+        for lane in 0..SignalBlock::LANES {
+            assert_eq!(block.get(lane), Signal::UncontrolledFalse);
+            block.set(lane, SIGNALS[lane % SIGNALS.len()]);
+        }
+        for lane in 0..SignalBlock::LANES {
+            assert_eq!(block.get(lane), SIGNALS[lane % SIGNALS.len()]);
+        }
+    }
+
+    #[test]
+    fn matches_scalar_truth_tables() {
+        let (lhs, rhs) = all_pairs();
+        let not = !lhs;
+        let and = lhs & rhs;
+        let or = lhs | rhs;
+        let xor = lhs ^ rhs;
+
+        for (i, a) in SIGNALS.iter().enumerate() {
+            for (j, b) in SIGNALS.iter().enumerate() {
+                let lane = i * SIGNALS.len() + j;
+                assert_eq!(not.get(lane), !*a, "!{a}");
+                assert_eq!(and.get(lane), *a & *b, "{a} & {b}");
+                assert_eq!(or.get(lane), *a | *b, "{a} | {b}");
+                assert_eq!(xor.get(lane), *a ^ *b, "{a} ^ {b}");
+            }
+        }
+    }
+}
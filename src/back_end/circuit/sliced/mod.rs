@@ -0,0 +1,192 @@
+use std::sync::OnceLock;
+
+use rayon::prelude::*;
+
+use super::operation::{Operation, SignalID};
+use super::signal::packed::SignalBlock;
+use super::signal::Signal;
+use super::TickContext;
+
+/// The state of a circuit packed one bit per lane, so every lane of an operation
+/// is computed at once with a handful of word operations.
+///
+/// Each operation owns `words` consecutive blocks and lane `l` is lane `l % 64` of
+/// its block `l / 64`. Lanes past the last one are kept at the default value so
+/// whole states can be compared block by block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Planes {
+    lanes: usize,
+    words: usize,
+    blocks: Box<[SignalBlock]>,
+}
+
+impl Planes {
+    /// Packs signals laid out as in `Circuit::inspect`.
+    pub fn pack(signals: &[Signal], lanes: usize) -> Self {
+        let words = lanes.div_ceil(SignalBlock::LANES);
+        let mut blocks = vec![SignalBlock::default(); signals.len() / lanes * words];
+        blocks
+            .par_chunks_mut(words)
+            .zip(signals.par_chunks(lanes))
+            .for_each(|(blocks, signals)| {
+                for (lane, signal) in signals.iter().enumerate() {
+                    blocks[lane / SignalBlock::LANES].set(lane % SignalBlock::LANES, *signal);
+                }
+            });
+        Self {
+            lanes,
+            words,
+            blocks: blocks.into_boxed_slice(),
+        }
+    }
+
+    /// Writes every lane back out in the layout of `Circuit::inspect`.
+    pub fn unpack_into(&self, signals: &mut [Signal]) {
+        signals
+            .par_chunks_mut(self.lanes)
+            .zip(self.blocks.par_chunks(self.words))
+            .for_each(|(signals, blocks)| {
+                for (lane, signal) in signals.iter_mut().enumerate() {
+                    *signal = blocks[lane / SignalBlock::LANES].get(lane % SignalBlock::LANES);
+                }
+            });
+    }
+
+    pub fn unpack(&self) -> Box<[Signal]> {
+        let length = self.blocks.len() / self.words.max(1) * self.lanes;
+        let mut signals = vec![Signal::Undefined; length].into_boxed_slice();
+        self.unpack_into(&mut signals);
+        signals
+    }
+
+    pub fn get(&self, id: SignalID, lane: usize) -> Signal {
+        let block = id.0 * self.words + lane / SignalBlock::LANES;
+        self.blocks[block].get(lane % SignalBlock::LANES)
+    }
+
+    /// Sets every lane of a signal to the same value.
+    pub fn fill(&mut self, id: SignalID, value: Signal) {
+        for word in 0..self.words {
+            self.blocks[id.0 * self.words + word] = self.clean(word, SignalBlock::splat(value));
+        }
+    }
+
+    fn block(&self, id: SignalID, word: usize) -> SignalBlock {
+        self.blocks[id.0 * self.words + word]
+    }
+
+    /// Resets the lanes of a block which are past the last lane.
+    fn clean(&self, word: usize, block: SignalBlock) -> SignalBlock {
+        match (word + 1) * SignalBlock::LANES > self.lanes {
+            true => {
+                let used = (1 << (self.lanes % SignalBlock::LANES)) - 1;
+                block.select(used, SignalBlock::default())
+            }
+            false => block,
+        }
+    }
+}
+
+/// Book keeping for the bit sliced kernel, whose state lives in the `planes` and
+/// `planes_swap` of the circuit in place of `signals` and `signals_swap`.
+///
+/// Gates are evaluated on whole blocks. Operations without a packed form call user
+/// closures or hold state, so they are evaluated one lane at a time as usual.
+#[derive(Debug, Default)]
+pub struct SlicedState {
+    /// set once the planes hold ticks which haven't been unpacked into the scalar
+    /// signals of the circuit
+    pub ahead: bool,
+    /// the current state unpacked for viewing while the planes are ahead
+    view: OnceLock<Box<[Signal]>>,
+}
+
+impl SlicedState {
+    /// The current state of the circuit, unpacked the first time it is asked for
+    /// after each tick.
+    pub fn view(&self, planes: &Planes) -> &[Signal] {
+        self.view.get_or_init(|| planes.unpack())
+    }
+
+    /// Writes the next state of the circuit into `swap`, reading the current state
+    /// from the planes of `context`.
+    pub fn tick(&mut self, context: &TickContext, swap: &mut Planes) {
+        self.view = OnceLock::new();
+        let planes = context
+            .planes
+            .expect("the bit sliced kernel reads from planes");
+        let words = planes.words;
+
+        swap.blocks
+            .par_chunks_mut(words)
+            .enumerate()
+            .for_each(|(index, blocks)| {
+                for (word, block) in blocks.iter_mut().enumerate() {
+                    let read = |id: &SignalID| planes.block(*id, word);
+                    let reduce = |ids: &[SignalID], identity, f: fn(_, _) -> _| {
+                        ids.iter().map(read).fold(SignalBlock::splat(identity), f)
+                    };
+                    let packed = match &context.description[index] {
+                        Operation::Not(a) => !read(a),
+                        Operation::And(a, b) => read(a) & read(b),
+                        Operation::Nand(a, b) => !(read(a) & read(b)),
+                        Operation::Or(a, b) => read(a) | read(b),
+                        Operation::Nor(a, b) => !(read(a) | read(b)),
+                        Operation::Xor(a, b) => read(a) ^ read(b),
+                        Operation::Xnor(a, b) => !(read(a) ^ read(b)),
+                        Operation::AndN(ids) => reduce(ids, Signal::True, |a, b| a & b),
+                        Operation::NandN(ids) => !reduce(ids, Signal::True, |a, b| a & b),
+                        Operation::OrN(ids) => reduce(ids, Signal::False, |a, b| a | b),
+                        Operation::NorN(ids) => !reduce(ids, Signal::False, |a, b| a | b),
+                        Operation::XorN(ids) => reduce(ids, Signal::False, |a, b| a ^ b),
+                        Operation::XnorN(ids) => !reduce(ids, Signal::False, |a, b| a ^ b),
+                        Operation::Mux(select, a, b) => {
+                            let select = read(select);
+                            (read(a) & !select) | (read(b) & select)
+                        }
+                        _ => {
+                            let first = word * SignalBlock::LANES;
+                            let last = (first + SignalBlock::LANES).min(planes.lanes);
+                            let mut block = SignalBlock::default();
+                            for lane in first..last {
+                                block.set(lane - first, context.evaluate(index, lane));
+                            }
+                            block
+                        }
+                    };
+                    *block = planes.clean(word, packed);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planes_round_trip() {
+        const SIGNALS: [Signal; 6] = [
+            Signal::False,
+            Signal::True,
+            Signal::UncontrolledFalse,
+            Signal::UncontrolledTrue,
+            Signal::HighImpedance,
+            Signal::Undefined,
+        ];
+        // two signals over a lane count which leaves most of the second word unused
+        let lanes = 70;
+        let signals: Vec<Signal> = (0..2 * lanes).map(|i| SIGNALS[i % 6]).collect();
+        let mut planes = Planes::pack(&signals, lanes);
+        assert_eq!(planes.words, 2);
+        assert_eq!(&*planes.unpack(), signals.as_slice());
+        assert_eq!(planes.get(SignalID(1), 69), signals[lanes + 69]);
+
+        planes.fill(SignalID(0), Signal::False);
+        assert!(planes.unpack()[..lanes].iter().all(|s| *s == Signal::False));
+        // the unused lanes are left alone so equal states compare equal
+        let mut refilled = Planes::pack(&signals, lanes);
+        refilled.fill(SignalID(0), Signal::False);
+        assert_eq!(planes, refilled);
+    }
+}
@@ -0,0 +1,379 @@
+/*
+On disk layout of a snapshot, all integers are little endian.
+
+    magic           6 bytes     "RZSNAP"
+    version         u16
+    operations      u64         number of operations in the description
+    structure hash  u64         see structure_hash
+    lanes           u64
+    ticks per input u64
+    tick counter    u128
+    signals         1 byte per signal (operations * lanes)
+    signals swap    1 byte per signal (operations * lanes)
+    memories        u64 count, then for each a u64 length followed by 1 byte per signal
+    delay lines     u64 count, then for each a scheduled byte (0xff for none), a u64
+                    count of pending transitions and for each a u128 tick and a signal
+
+Version 1 files end after the signals swap, they can only be restored into circuits
+without memories. Version 2 files end after the memories, delay lines restored from
+them start out with nothing in flight.
+
+Closures can't be compared so the structure hash only covers the type of each operation and
+the IDs it reads from. That is enough to catch a snapshot being loaded into the wrong design,
+but swapping the closure behind an input is the user's business.
+*/
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::delay::DelayLine;
+use super::operation::Operation;
+use super::signal::Signal;
+
+const MAGIC: &[u8; 6] = b"RZSNAP";
+const VERSION: u16 = 3;
+
+/// A copy of the full simulation state of a `Circuit`.
+///
+/// Produced by `Circuit::snapshot` and loaded back with `Circuit::restore`. A snapshot
+/// may be restored into any circuit built from the same description, which allows a
+/// simulation to be resumed later or forked into several independent runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub(super) operations: usize,
+    pub(super) structure_hash: u64,
+    pub(super) lanes: usize,
+    pub(super) ticks_per_input: usize,
+    pub(super) tick_counter: u128,
+    pub(super) signals: Box<[Signal]>,
+    pub(super) signals_swap: Box<[Signal]>,
+    /// contents of every memory block, in the order they appear in the description
+    pub(super) memories: Box<[Box<[Signal]>]>,
+    /// transitions in flight on delayed operations, empty when the circuit has none
+    pub(super) delay_lines: Box<[DelayLine]>,
+}
+
+impl Snapshot {
+    /// Gets the tick the simulation was on when the snapshot was taken.
+    pub fn get_tick(&self) -> u128 {
+        self.tick_counter
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.operations as u64).to_le_bytes())?;
+        writer.write_all(&self.structure_hash.to_le_bytes())?;
+        writer.write_all(&(self.lanes as u64).to_le_bytes())?;
+        writer.write_all(&(self.ticks_per_input as u64).to_le_bytes())?;
+        writer.write_all(&self.tick_counter.to_le_bytes())?;
+
+        let signals: Vec<u8> = self.signals.iter().map(|s| encode(*s)).collect();
+        writer.write_all(&signals)?;
+        let signals_swap: Vec<u8> = self.signals_swap.iter().map(|s| encode(*s)).collect();
+        writer.write_all(&signals_swap)?;
+
+        writer.write_all(&(self.memories.len() as u64).to_le_bytes())?;
+        for memory in self.memories.iter() {
+            writer.write_all(&(memory.len() as u64).to_le_bytes())?;
+            let contents: Vec<u8> = memory.iter().map(|s| encode(*s)).collect();
+            writer.write_all(&contents)?;
+        }
+
+        writer.write_all(&(self.delay_lines.len() as u64).to_le_bytes())?;
+        for line in self.delay_lines.iter() {
+            writer.write_all(&[line.scheduled.map_or(NONE, encode)])?;
+            writer.write_all(&(line.pending.len() as u64).to_le_bytes())?;
+            for (due, value) in line.pending.iter() {
+                writer.write_all(&due.to_le_bytes())?;
+                writer.write_all(&[encode(*value)])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, SnapshotError> {
+        let mut magic = [0; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if !(1..=VERSION).contains(&version) {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let operations = read_u64(reader)? as usize;
+        let structure_hash = read_u64(reader)?;
+        let lanes = read_u64(reader)? as usize;
+        let ticks_per_input = read_u64(reader)? as usize;
+        let mut tick_counter = [0; 16];
+        reader.read_exact(&mut tick_counter)?;
+        let tick_counter = u128::from_le_bytes(tick_counter);
+
+        let length = match operations.checked_mul(lanes) {
+            Some(t) => t,
+            None => return Err(SnapshotError::Corrupt),
+        };
+        let signals = read_signals(reader, length)?;
+        let signals_swap = read_signals(reader, length)?;
+
+        let mut memories = Vec::new();
+        if version >= 2 {
+            for _ in 0..read_u64(reader)? {
+                let length = read_u64(reader)? as usize;
+                memories.push(read_signals(reader, length)?);
+            }
+        }
+
+        let mut delay_lines = Vec::new();
+        if version >= 3 {
+            for _ in 0..read_u64(reader)? {
+                let scheduled = match read_byte(reader)? {
+                    NONE => None,
+                    byte => Some(decode(byte)?),
+                };
+                let mut pending = VecDeque::new();
+                for _ in 0..read_u64(reader)? {
+                    let mut due = [0; 16];
+                    reader.read_exact(&mut due)?;
+                    let value = decode(read_byte(reader)?)?;
+                    pending.push_back((u128::from_le_bytes(due), value));
+                }
+                delay_lines.push(DelayLine { pending, scheduled });
+            }
+        }
+
+        Ok(Self {
+            operations,
+            structure_hash,
+            lanes,
+            ticks_per_input,
+            tick_counter,
+            signals,
+            signals_swap,
+            memories: memories.into_boxed_slice(),
+            delay_lines: delay_lines.into_boxed_slice(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+        Self::read_from(&mut file)
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file contains an invalid signal or impossible sizes.
+    Corrupt,
+    OperationCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    StructureMismatch,
+    LaneMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::BadMagic => write!(f, "not a snapshot file"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            Self::Corrupt => write!(f, "snapshot is corrupt"),
+            Self::OperationCountMismatch { expected, found } => write!(
+                f,
+                "snapshot has {found} operations but the circuit has {expected}"
+            ),
+            Self::StructureMismatch => write!(f, "snapshot was taken from a different circuit"),
+            Self::LaneMismatch { expected, found } => write!(
+                f,
+                "snapshot has {found} lanes but the circuit has {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Hashes the shape of a description, the type of each operation and the signals it
+/// reads, using 64 bit FNV-1a so the value is stable between builds.
+pub fn structure_hash(description: &[Operation]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    for op in description {
+        hash_op(op, &mut feed);
+    }
+
+    hash
+}
+
+fn hash_op(op: &Operation, feed: &mut impl FnMut(&[u8])) {
+    let tag: u8 = match op {
+        Operation::Input(_) => 0,
+        Operation::Output(_, _) => 1,
+        Operation::LaneInput(_) => 2,
+        Operation::LaneOutput(_, _) => 3,
+        Operation::Not(_) => 4,
+        Operation::And(_, _) => 5,
+        Operation::Nand(_, _) => 6,
+        Operation::Or(_, _) => 7,
+        Operation::Nor(_, _) => 8,
+        Operation::Xor(_, _) => 9,
+        Operation::Xnor(_, _) => 10,
+        Operation::Delayed(_, _) => 11,
+        Operation::TriBuf(_, _) => 12,
+        Operation::Resolve(_) => 13,
+        Operation::AndN(_) => 14,
+        Operation::NandN(_) => 15,
+        Operation::OrN(_) => 16,
+        Operation::NorN(_) => 17,
+        Operation::XorN(_) => 18,
+        Operation::XnorN(_) => 19,
+        Operation::Mux(_, _, _) => 20,
+        Operation::Lut(_, _) => 21,
+        Operation::Dff(_) => 22,
+        Operation::DLatch(_, _) => 23,
+        Operation::Memory(_) => 24,
+        Operation::MemRead(_, _) => 25,
+        Operation::Stimulus(_) => 26,
+    };
+    feed(&[tag]);
+
+    match op {
+        Operation::Delayed(inner, delay) => {
+            feed(&(delay.ticks as u64).to_le_bytes());
+            feed(&[delay.kind as u8]);
+            hash_op(inner, feed);
+        }
+        Operation::Dff(ff) => {
+            // the optional controls are marked so a reset can't be mistaken for a set
+            feed(&[ff.reset.is_some() as u8, ff.set.is_some() as u8]);
+            for source in op.inputs() {
+                feed(&(source.0 as u64).to_le_bytes());
+            }
+        }
+        Operation::Memory(block) => {
+            feed(&(block.width as u64).to_le_bytes());
+            feed(&(block.address.len() as u64).to_le_bytes());
+            feed(&(block.data_in.len() as u64).to_le_bytes());
+            feed(&[
+                block.write_enable.is_some() as u8,
+                block.clock.is_some() as u8,
+                block.read as u8,
+            ]);
+            for source in op.inputs() {
+                feed(&(source.0 as u64).to_le_bytes());
+            }
+        }
+        Operation::MemRead(memory, bit) => {
+            feed(&(memory.0 as u64).to_le_bytes());
+            feed(&(*bit as u64).to_le_bytes());
+        }
+        Operation::Lut(inputs, table) => {
+            feed(&table.to_le_bytes());
+            feed(&(inputs.len() as u64).to_le_bytes());
+            for source in inputs.iter() {
+                feed(&(source.0 as u64).to_le_bytes());
+            }
+        }
+        Operation::AndN(inputs)
+        | Operation::NandN(inputs)
+        | Operation::OrN(inputs)
+        | Operation::NorN(inputs)
+        | Operation::XorN(inputs)
+        | Operation::XnorN(inputs)
+        | Operation::Resolve(inputs) => {
+            // variadic operations need their length so neighbours can't shift between them
+            feed(&(inputs.len() as u64).to_le_bytes());
+            for source in inputs.iter() {
+                feed(&(source.0 as u64).to_le_bytes());
+            }
+        }
+        _ => {
+            for source in op.inputs() {
+                feed(&(source.0 as u64).to_le_bytes());
+            }
+        }
+    }
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, SnapshotError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8, SnapshotError> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_signals(reader: &mut impl Read, length: usize) -> Result<Box<[Signal]>, SnapshotError> {
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(SnapshotError::Corrupt);
+    }
+    bytes.into_iter().map(decode).collect()
+}
+
+/// Marks a delay line with no scheduled value, past every encoded signal.
+const NONE: u8 = 0xff;
+
+fn encode(signal: Signal) -> u8 {
+    match signal {
+        Signal::False => 0,
+        Signal::True => 1,
+        Signal::UncontrolledFalse => 2,
+        Signal::UncontrolledTrue => 3,
+        Signal::HighImpedance => 4,
+        Signal::Undefined => 5,
+    }
+}
+
+fn decode(byte: u8) -> Result<Signal, SnapshotError> {
+    match byte {
+        0 => Ok(Signal::False),
+        1 => Ok(Signal::True),
+        2 => Ok(Signal::UncontrolledFalse),
+        3 => Ok(Signal::UncontrolledTrue),
+        4 => Ok(Signal::HighImpedance),
+        5 => Ok(Signal::Undefined),
+        _ => Err(SnapshotError::Corrupt),
+    }
+}
@@ -0,0 +1,491 @@
+/*
+Text format of a stimulus, one per line:
+
+    const <value>                       holds one value forever
+    clock <low> <high> <phase>          low then high for the given number of samples,
+                                        starting <phase> samples into the period
+    wave <values>                       one value per sample then holds the last
+    repeat <values>                     one value per sample repeating forever
+    edges <initial> <sample>:<value>... changes value at each listed sample
+
+Values are written as single characters, 0 and 1 for false and true, f and t for
+their uncontrolled forms, z for high impedance and x for undefined. A file of
+stimuli puts the ID of the input being driven in front of each line, or its
+hierarchical name when written by `Circuit::write_stimuli`. Blank lines and lines
+starting with # are skipped.
+*/
+
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::operation::SignalID;
+use super::signal::Signal;
+
+/// An input described by data rather than a closure, so it can be compared, saved
+/// and evaluated without calling back into user code. Stimuli are evaluated per
+/// input sample like `Operation::Input` and drive every lane with the same value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Stimulus {
+    Constant(Signal),
+    /// False for `low` samples then true for `high` samples, shifted so that sample 0
+    /// lands `phase` samples into the period. A period of zero or one too long for a
+    /// `u128` drives `Undefined`.
+    Clock {
+        low: u128,
+        high: u128,
+        phase: u128,
+    },
+    /// One value per sample, past the end the waveform either repeats or holds its
+    /// last value.
+    Waveform {
+        samples: Box<[Signal]>,
+        repeat: bool,
+    },
+    /// Starts at `initial` and changes at each `(sample, value)`, sorted by sample.
+    Edges {
+        initial: Signal,
+        edges: Box<[(u128, Signal)]>,
+    },
+}
+
+impl Stimulus {
+    /// The value driven on the given input sample.
+    pub fn value(&self, sample: u128) -> Signal {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Clock { low, high, phase } => match low.checked_add(*high) {
+                Some(period) if period > 0 => {
+                    // added modulo the period so large phases can't overflow
+                    let (sample, phase) = (sample % period, phase % period);
+                    let position = match sample >= period - phase {
+                        true => sample - (period - phase),
+                        false => sample + phase,
+                    };
+                    match position < *low {
+                        true => Signal::False,
+                        false => Signal::True,
+                    }
+                }
+                _ => Signal::Undefined,
+            },
+            Self::Waveform { samples, repeat } => match (samples.len() as u128, repeat) {
+                (0, _) => Signal::Undefined,
+                (length, true) => samples[(sample % length) as usize],
+                (length, false) => samples[sample.min(length - 1) as usize],
+            },
+            Self::Edges { initial, edges } => {
+                match edges.partition_point(|(at, _)| *at <= sample) {
+                    0 => *initial,
+                    passed => edges[passed - 1].1,
+                }
+            }
+        }
+    }
+
+    /// Reads a dense waveform to play back once, one value per sample. Whitespace
+    /// is ignored so captures can be wrapped over several lines.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, StimulusError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::Waveform {
+            samples: parse_values(text.split_whitespace().flat_map(str::chars))?,
+            repeat: false,
+        })
+    }
+}
+
+impl Display for Stimulus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(value) => write!(f, "const {}", to_char(*value)),
+            Self::Clock { low, high, phase } => write!(f, "clock {low} {high} {phase}"),
+            Self::Waveform { samples, repeat } => {
+                write!(f, "{}", if *repeat { "repeat " } else { "wave " })?;
+                samples
+                    .iter()
+                    .try_for_each(|s| write!(f, "{}", to_char(*s)))
+            }
+            Self::Edges { initial, edges } => {
+                write!(f, "edges {}", to_char(*initial))?;
+                edges
+                    .iter()
+                    .try_for_each(|(at, value)| write!(f, " {at}:{}", to_char(*value)))
+            }
+        }
+    }
+}
+
+impl FromStr for Stimulus {
+    type Err = StimulusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || StimulusError::Syntax(s.to_string());
+        let number = |word: Option<&str>| word.and_then(|w| w.parse::<u128>().ok()).ok_or_else(bad);
+        let value = |word: Option<&str>| match word.map(|w| w.chars().collect::<Vec<_>>()) {
+            Some(chars) if chars.len() == 1 => from_char(chars[0]),
+            _ => Err(bad()),
+        };
+
+        let mut words = s.split_whitespace();
+        let stimulus = match words.next() {
+            Some("const") => Self::Constant(value(words.next())?),
+            Some("clock") => {
+                let (low, high, phase) = (
+                    number(words.next())?,
+                    number(words.next())?,
+                    number(words.next())?,
+                );
+                if !matches!(low.checked_add(high), Some(period) if period > 0) {
+                    return Err(bad());
+                }
+                Self::Clock { low, high, phase }
+            }
+            Some(kind @ ("wave" | "repeat")) => Self::Waveform {
+                samples: parse_values(words.next().unwrap_or("").chars())?,
+                repeat: kind == "repeat",
+            },
+            Some("edges") => {
+                let initial = value(words.next())?;
+                let edges = words
+                    .by_ref()
+                    .map(|word| {
+                        let (at, v) = word.split_once(':').ok_or_else(bad)?;
+                        Ok((number(Some(at))?, value(Some(v))?))
+                    })
+                    .collect::<Result<Box<[_]>, StimulusError>>()?;
+                if edges.windows(2).any(|pair| pair[0].0 > pair[1].0) {
+                    return Err(bad());
+                }
+                Self::Edges { initial, edges }
+            }
+            _ => return Err(bad()),
+        };
+        match words.next() {
+            None => Ok(stimulus),
+            Some(_) => Err(bad()),
+        }
+    }
+}
+
+/// Writes the stimulus of each input in the text format above.
+pub fn write_to(stimuli: &[(SignalID, Stimulus)], writer: &mut impl Write) -> std::io::Result<()> {
+    for (id, stimulus) in stimuli {
+        writeln!(writer, "{} {stimulus}", id.0)?;
+    }
+    Ok(())
+}
+
+/// Reads back stimuli written by `write_to`.
+pub fn read_from(reader: &mut impl BufRead) -> Result<Vec<(SignalID, Stimulus)>, StimulusError> {
+    read_bindings(reader)?
+        .into_iter()
+        .map(|(input, stimulus)| match input.parse() {
+            Ok(id) => Ok((SignalID(id), stimulus)),
+            Err(_) => Err(StimulusError::Syntax(format!("{input} {stimulus}"))),
+        })
+        .collect()
+}
+
+/// Reads stimuli along with the input each line gives, as written, whether that is
+/// an ID or a name.
+pub fn read_bindings(reader: &mut impl BufRead) -> Result<Vec<(String, Stimulus)>, StimulusError> {
+    let mut stimuli = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (input, stimulus) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| StimulusError::Syntax(line.to_string()))?;
+        stimuli.push((input.to_string(), stimulus.parse()?));
+    }
+    Ok(stimuli)
+}
+
+pub fn save(stimuli: &[(SignalID, Stimulus)], path: impl AsRef<Path>) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_to(stimuli, &mut file)?;
+    file.flush()
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<(SignalID, Stimulus)>, StimulusError> {
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    read_from(&mut file)
+}
+
+fn parse_values(chars: impl Iterator<Item = char>) -> Result<Box<[Signal]>, StimulusError> {
+    chars.map(from_char).collect()
+}
+
+fn to_char(signal: Signal) -> char {
+    match signal {
+        Signal::False => '0',
+        Signal::True => '1',
+        Signal::UncontrolledFalse => 'f',
+        Signal::UncontrolledTrue => 't',
+        Signal::HighImpedance => 'z',
+        Signal::Undefined => 'x',
+    }
+}
+
+fn from_char(c: char) -> Result<Signal, StimulusError> {
+    match c {
+        '0' => Ok(Signal::False),
+        '1' => Ok(Signal::True),
+        'f' => Ok(Signal::UncontrolledFalse),
+        't' => Ok(Signal::UncontrolledTrue),
+        'z' => Ok(Signal::HighImpedance),
+        'x' => Ok(Signal::Undefined),
+        _ => Err(StimulusError::BadValue(c)),
+    }
+}
+
+#[derive(Debug)]
+pub enum StimulusError {
+    Io(std::io::Error),
+    /// A line which isn't a stimulus.
+    Syntax(String),
+    /// A character which isn't one of the signal values.
+    BadValue(char),
+    /// A line given to a circuit whose input is neither a known name nor an ID.
+    UnknownInput(String),
+    /// A stimulus bound to something other than an input.
+    NotAnInput(SignalID),
+}
+
+impl From<std::io::Error> for StimulusError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Display for StimulusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Syntax(line) => write!(f, "not a stimulus: {line}"),
+            Self::BadValue(c) => write!(f, "{c:?} is not a signal value"),
+            Self::UnknownInput(input) => write!(f, "no input is named {input}"),
+            Self::NotAnInput(id) => write!(f, "signal {} is not an input", id.0),
+        }
+    }
+}
+
+impl std::error::Error for StimulusError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::circuit::builder::GateLevelDescription;
+    use crate::back_end::circuit::{Circuit, Kernel};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use Signal::*;
+
+    fn samples(stimulus: &Stimulus, count: u128) -> Vec<Signal> {
+        (0..count).map(|sample| stimulus.value(sample)).collect()
+    }
+
+    #[test]
+    fn values_and_text() {
+        let clock = Stimulus::Clock {
+            low: 2,
+            high: 1,
+            phase: 1,
+        };
+        let wave = Stimulus::Waveform {
+            samples: Box::new([True, HighImpedance, False]),
+            repeat: false,
+        };
+        let repeat = Stimulus::Waveform {
+            samples: Box::new([True, False]),
+            repeat: true,
+        };
+        let edges = Stimulus::Edges {
+            initial: Undefined,
+            edges: Box::new([(2, True), (4, UncontrolledFalse)]),
+        };
+
+        assert_eq!(samples(&Stimulus::Constant(True), 2), [True, True]);
+        assert_eq!(samples(&clock, 6), [False, True, False, False, True, False]);
+        let late = Stimulus::Clock {
+            low: 2,
+            high: 1,
+            phase: u128::MAX,
+        };
+        // u128::MAX is a multiple of the period so the phase cancels out
+        assert_eq!(
+            [late.value(u128::MAX), late.value(u128::MAX - 1)],
+            [False, True]
+        );
+        assert_eq!(
+            samples(&wave, 5),
+            [True, HighImpedance, False, False, False]
+        );
+        assert_eq!(samples(&repeat, 3), [True, False, True]);
+        assert_eq!(
+            samples(&edges, 6),
+            [
+                Undefined,
+                Undefined,
+                True,
+                True,
+                UncontrolledFalse,
+                UncontrolledFalse
+            ]
+        );
+
+        let stimuli = vec![
+            (SignalID(0), Stimulus::Constant(True)),
+            (SignalID(3), clock),
+            (SignalID(1), wave),
+            (SignalID(7), repeat),
+            (SignalID(2), edges),
+        ];
+        let mut text = Vec::new();
+        write_to(&stimuli, &mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text.clone()).unwrap(),
+            "0 const 1\n3 clock 2 1 1\n1 wave 1z0\n7 repeat 10\n2 edges x 2:1 4:f\n"
+        );
+        assert_eq!(read_from(&mut text.as_slice()).unwrap(), stimuli);
+
+        let commented = "# clocks\n\n4 clock 1 1 0\n";
+        assert_eq!(read_from(&mut commented.as_bytes()).unwrap().len(), 1);
+        for bad in [
+            "clock 0 0 0",
+            "wave 01q",
+            "edges 0 3:1 1:0",
+            "const 1 1",
+            "pulse",
+            "clock 340282366920938463463374607431768211455 1 0",
+            "clock 1 340282366920938463463374607431768211455 0",
+        ] {
+            assert!(bad.parse::<Stimulus>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn drives_circuits_like_closures() {
+        // the same xor of a clock and a waveform, once from closures and once from stimuli
+        let build = |declarative: bool| {
+            let mut gld = GateLevelDescription::new();
+            let clk = gld.rz_alloc();
+            let data = gld.rz_alloc();
+            let y = gld.rz_alloc();
+            match declarative {
+                true => {
+                    gld.mk_stimulus(
+                        clk,
+                        Stimulus::Clock {
+                            low: 1,
+                            high: 1,
+                            phase: 0,
+                        },
+                    )
+                    .unwrap();
+                    gld.mk_stimulus(data, "wave 0011x1".parse().unwrap())
+                        .unwrap();
+                }
+                false => {
+                    gld.mk_input(
+                        clk,
+                        Arc::new(|_, sample| match sample % 2 {
+                            0 => False,
+                            _ => True,
+                        }),
+                    )
+                    .unwrap();
+                    gld.mk_input(
+                        data,
+                        Arc::new(|_, sample| match sample {
+                            0 | 1 => False,
+                            4 => Undefined,
+                            _ => True,
+                        }),
+                    )
+                    .unwrap();
+                }
+            }
+            gld.mk_xor(y, clk, data).unwrap();
+            gld.into_desc().unwrap()
+        };
+
+        for kernel in [Kernel::FullSweep, Kernel::EventDriven, Kernel::BitSliced] {
+            let mut closures = Circuit::new(build(false), 3).with_kernel(kernel);
+            let mut stimuli = Circuit::new(build(true), 3).with_kernel(kernel);
+            for _ in 0..24 {
+                closures.tick();
+                stimuli.tick();
+                assert_eq!(closures.inspect().1, stimuli.inspect().1);
+            }
+        }
+
+        let circuit = Circuit::new(build(true), 3);
+        assert_eq!(circuit.stimuli()[1].1.to_string(), "wave 0011x1");
+    }
+
+    #[test]
+    fn replays_saved_stimuli_by_name() {
+        // a flip-flop sampling data on a clock, with named inputs
+        let build = |declarative: bool| {
+            let mut gld = GateLevelDescription::new();
+            let [clk, data, q] = [gld.rz_alloc(), gld.rz_alloc(), gld.rz_alloc()];
+            match declarative {
+                true => {
+                    gld.mk_stimulus(clk, "repeat 01".parse().unwrap()).unwrap();
+                    gld.mk_stimulus(data, "edges 0 3:1 7:x".parse().unwrap())
+                        .unwrap();
+                }
+                false => {
+                    gld.mk_input(clk, Arc::new(|_, _| Undefined)).unwrap();
+                    gld.mk_input(data, Arc::new(|_, _| Undefined)).unwrap();
+                }
+            }
+            gld.mk_dff(q, data, clk, None, None).unwrap();
+            let names = HashMap::from([
+                ("top.clk".to_string(), clk),
+                ("top.data".to_string(), data),
+                ("top.q".to_string(), q),
+            ]);
+            Circuit::new(gld.into_desc().unwrap(), 2).with_names(names)
+        };
+
+        let path = std::env::temp_dir().join(format!("stimuli-{}.txt", std::process::id()));
+        let mut recorded = build(true);
+        recorded.save_stimuli(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "top.clk repeat 01\ntop.data edges 0 3:1 7:x\n");
+
+        let mut replayed = build(false);
+        replayed.load_stimuli(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replayed.stimuli(), recorded.stimuli());
+        for _ in 0..24 {
+            recorded.tick();
+            replayed.tick();
+            assert_eq!(replayed.inspect().1, recorded.inspect().1);
+        }
+
+        // nothing is driven unless every line is bound to an input
+        let mut circuit = build(false);
+        let unknown = "top.clk const 1\ntop.rst const 0\n";
+        assert!(matches!(
+            circuit.read_stimuli(&mut unknown.as_bytes()),
+            Err(StimulusError::UnknownInput(name)) if name == "top.rst"
+        ));
+        let q = circuit.lookup("top.q").unwrap();
+        for not_input in ["top.q const 1\n", &format!("{} const 1\n", q.0)] {
+            assert!(matches!(
+                circuit.read_stimuli(&mut not_input.as_bytes()),
+                Err(StimulusError::NotAnInput(id)) if id == q
+            ));
+        }
+        assert!(circuit.stimuli().is_empty());
+        circuit.read_stimuli(&mut "0 const 1\n".as_bytes()).unwrap();
+        assert_eq!(circuit.stimuli(), [(SignalID(0), Stimulus::Constant(True))]);
+    }
+}
@@ -256,6 +256,297 @@ impl Cell for Inverter {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct AndNGate {
+    pub inputs: usize,
+}
+impl Cell for AndNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::AndN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("&", self.inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NandNGate {
+    pub inputs: usize,
+}
+impl Cell for NandNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::NandN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("~&", self.inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OrNGate {
+    pub inputs: usize,
+}
+impl Cell for OrNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::OrN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("|", self.inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NorNGate {
+    pub inputs: usize,
+}
+impl Cell for NorNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::NorN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("~|", self.inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct XorNGate {
+    pub inputs: usize,
+}
+impl Cell for XorNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::XorN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("^", self.inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct XnorNGate {
+    pub inputs: usize,
+}
+impl Cell for XnorNGate {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::XnorN)
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("~^", self.inputs)
+    }
+}
+
+/// Passes A while S is false and B while S is true.
+#[derive(Clone, Copy, Debug)]
+pub struct Mux {}
+impl Cell for Mux {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::Mux)
+    }
+
+    fn interface(&self) -> CellInterface {
+        let interface = [
+            Port {
+                name: "Y".to_string(),
+                port_type: PortType::Output,
+                local_location: Address(CellHandle(0), PortHandle(0)),
+            },
+            Port {
+                name: "S".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(1)),
+            },
+            Port {
+                name: "A".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(2)),
+            },
+            Port {
+                name: "B".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(3)),
+            },
+        ];
+        // type should be primitive so local location should be disregarded
+        CellInterface::Builtin(Box::new(interface))
+    }
+}
+
+/// A lookup table with `inputs` inputs, bit `i` of `table` is the output when
+/// input `k` is bit `k` of `i`.
+#[derive(Clone, Copy, Debug)]
+pub struct Lut {
+    pub inputs: usize,
+    pub table: u64,
+}
+impl Cell for Lut {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::Lut(self.table))
+    }
+
+    fn interface(&self) -> CellInterface {
+        std_interface::variadic("lut", self.inputs)
+    }
+}
+
+/// A rising edge triggered D flip-flop. The RST and SET ports are only present
+/// when enabled, in that order after D and CLK.
+#[derive(Clone, Copy, Debug)]
+pub struct Dff {
+    pub reset: bool,
+    pub set: bool,
+}
+impl Cell for Dff {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::Dff {
+            reset: self.reset,
+            set: self.set,
+        })
+    }
+
+    fn interface(&self) -> CellInterface {
+        let mut interface = vec![
+            Port {
+                name: "Q".to_string(),
+                port_type: PortType::Output,
+                local_location: Address(CellHandle(0), PortHandle(0)),
+            },
+            Port {
+                name: "D".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(1)),
+            },
+            Port {
+                name: "CLK".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(2)),
+            },
+        ];
+        for (present, name) in [(self.reset, "RST"), (self.set, "SET")] {
+            if present {
+                interface.push(Port {
+                    name: name.to_string(),
+                    port_type: PortType::Input,
+                    local_location: Address(CellHandle(0), PortHandle(interface.len())),
+                });
+            }
+        }
+        // type should be primitive so local location should be disregarded
+        CellInterface::Builtin(interface.into_boxed_slice())
+    }
+}
+
+/// A level sensitive latch, transparent while EN is true.
+#[derive(Clone, Copy, Debug)]
+pub struct DLatch {}
+impl Cell for DLatch {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::DLatch)
+    }
+
+    fn interface(&self) -> CellInterface {
+        let interface = [
+            Port {
+                name: "Q".to_string(),
+                port_type: PortType::Output,
+                local_location: Address(CellHandle(0), PortHandle(0)),
+            },
+            Port {
+                name: "D".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(1)),
+            },
+            Port {
+                name: "EN".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(2)),
+            },
+        ];
+        // type should be primitive so local location should be disregarded
+        CellInterface::Builtin(Box::new(interface))
+    }
+}
+
+/// Drives A onto Y while EN is true, otherwise leaves Y at high impedance. The
+/// outputs of several of these may share a wire, see `Module::buses`.
+#[derive(Clone, Copy, Debug)]
+pub struct TriBuf {}
+impl Cell for TriBuf {
+    fn clone_as_box(&self) -> Box<dyn Cell> {
+        Box::new(*self)
+    }
+
+    fn contents(&self) -> CellContents {
+        CellContents::Primitive(PrimitiveType::TriBuf)
+    }
+
+    fn interface(&self) -> CellInterface {
+        let interface = [
+            Port {
+                name: "Y".to_string(),
+                port_type: PortType::Output,
+                local_location: Address(CellHandle(0), PortHandle(0)),
+            },
+            Port {
+                name: "A".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(1)),
+            },
+            Port {
+                name: "EN".to_string(),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(2)),
+            },
+        ];
+        // type should be primitive so local location should be disregarded
+        CellInterface::Builtin(Box::new(interface))
+    }
+}
+
 // primitive non gate components
 /// A cell which reproduces the series of signals in waveform after setup_time ticks have elapsed.
 #[derive(Clone, Debug)]
@@ -400,14 +691,14 @@ impl Cell for Assert {
     fn contents(&self) -> CellContents {
         let setup_time = self.setup_time.clone();
         let waveform = self.waveform.clone().into_boxed_slice();
-        let expr = Arc::new(move |index, tick, signal| {
+        let expr = Arc::new(move |index, tick, lane, signal| {
             if tick >= setup_time {
                 let waveform_idx = ((tick - setup_time) % (waveform.len() as u128)) as usize;
                 let expected = *waveform.get(waveform_idx).unwrap();
-                assert_eq!(signal, expected);
+                assert_eq!(signal, expected, "index: {index} lane: {lane} at {tick}");
             }
         });
-        CellContents::Primitive(PrimitiveType::Output(expr))
+        CellContents::Primitive(PrimitiveType::LaneOutput(expr))
     }
 }
 
@@ -438,7 +729,7 @@ impl Cell for DeltaAssert {
         let period = self.period.clone();
         let setup_time = self.setup_time.clone();
         let phase_offset = self.phase_offset.clone();
-        let expr = Arc::new(move |_index, tick, signal| {
+        let expr = Arc::new(move |index, tick, lane, signal| {
             if tick >= setup_time {
                 let waveform_position = (tick - phase_offset) % period;
                 let mut previous_edge = (0, Signal::Undefined);
@@ -448,10 +739,13 @@ impl Cell for DeltaAssert {
                     }
                 }
                 let expected_signal = previous_edge.1;
-                assert_eq!(signal, expected_signal);
+                assert_eq!(
+                    signal, expected_signal,
+                    "index: {index} lane: {lane} at {tick}"
+                );
             }
         });
-        CellContents::Primitive(PrimitiveType::Output(expr))
+        CellContents::Primitive(PrimitiveType::LaneOutput(expr))
     }
 }
 
@@ -474,4 +768,21 @@ mod std_interface {
         ];
         CellInterface::Builtin(Box::new(interface))
     }
+
+    /// An output followed by `inputs` inputs named I0, I1, ...
+    pub fn variadic(operator: &str, inputs: usize) -> CellInterface {
+        let output = Port {
+            name: format!("{operator}I"),
+            port_type: PortType::Output,
+            local_location: Address(CellHandle(0), PortHandle(0)),
+        };
+        let interface = std::iter::once(output)
+            .chain((0..inputs).map(|idx| Port {
+                name: format!("I{idx}"),
+                port_type: PortType::Input,
+                local_location: Address(CellHandle(0), PortHandle(idx + 1)),
+            }))
+            .collect();
+        CellInterface::Builtin(interface)
+    }
 }
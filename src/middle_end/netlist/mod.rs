@@ -8,7 +8,7 @@ translation.
 
 pub mod cell_types;
 
-use crate::back_end::circuit::{self, operation::SignalID};
+use crate::back_end::circuit::{self, operation::SignalID, signal::Signal};
 use std::{collections::HashMap, fmt::Debug, iter, sync::Arc};
 
 #[derive(Debug)]
@@ -38,7 +38,14 @@ impl Netlist {
 
         self.lower(&mut gld, top, port_allocations)?;
 
-        Result::Ok(circuit::Circuit::new(gld.into_desc(), input_tick_ratio))
+        let description = match gld.into_desc() {
+            Ok(t) => t,
+            Err(e) => {
+                return Result::Err(NetlistLowerError::Builder(e));
+            }
+        };
+
+        Result::Ok(circuit::Circuit::new(description, input_tick_ratio))
     }
 
     /// A recursive function which builds an instance of the provided module in
@@ -57,6 +64,8 @@ impl Netlist {
 
         // Setup namespace to keep track of where has been allocated.
         let mut name_space: HashMap<Address, SignalID> = HashMap::new();
+        // buses get a resolved net each, shared by every drain on the same set of sources
+        let mut bus_space: HashMap<Vec<Source>, SignalID> = HashMap::new();
 
         // intitialize namespace by ensuring all allocations from
         // parent module are added.
@@ -90,6 +99,53 @@ impl Netlist {
                 let signal_id = match port_desc.port_type {
                     PortType::Input => {
                         // lookup source address or allocate and add it to namespace if needed
+                        if let Some(sources) = module.buses.get(&Drain(current_address)) {
+                            let mut sources = sources.clone();
+                            sources.sort();
+                            sources.dedup();
+
+                            let sig = match bus_space.get(&sources) {
+                                Some(sig) => *sig,
+                                None => {
+                                    let mut drivers = Vec::new();
+                                    for source in sources.iter() {
+                                        match module
+                                            .cells
+                                            .get((source.0).0 .0)
+                                            .map(|c| c.contents())
+                                        {
+                                            Some(CellContents::Primitive(
+                                                PrimitiveType::TriBuf,
+                                            )) => (),
+                                            _ => {
+                                                return Result::Err(
+                                                    NetlistLowerError::BusDriverNotTriState(
+                                                        *source,
+                                                    ),
+                                                );
+                                            }
+                                        }
+                                        drivers.push(match name_space.get(&source.0) {
+                                            Some(sig) => *sig,
+                                            None => {
+                                                let sig = gld.rz_alloc();
+                                                name_space.insert(source.0, sig);
+                                                sig
+                                            }
+                                        });
+                                    }
+
+                                    let sig = gld.rz_alloc();
+                                    gld.mk_resolve(sig, &drivers)
+                                        .map_err(NetlistLowerError::Builder)?;
+                                    bus_space.insert(sources, sig);
+                                    sig
+                                }
+                            };
+                            child_port_mapping.push(sig);
+                            continue;
+                        }
+
                         match module.wires.get(&Drain(current_address)) {
                             Option::Some(source_address) => match name_space.get(&source_address.0)
                             {
@@ -106,7 +162,10 @@ impl Netlist {
                                 // since if they want a high impedance link it should be
                                 // added to their design explicitly not via fallback allocation
 
-                                gld.rz_alloc()
+                                let sig = gld.rz_alloc();
+                                gld.mk_input(sig, Arc::new(|_, _| Signal::HighImpedance))
+                                    .map_err(NetlistLowerError::Builder)?;
+                                sig
                             }
                         }
                     }
@@ -177,7 +236,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
 
                         // binary
@@ -208,7 +267,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Nand => {
                             gld.mk_nand(
@@ -237,7 +296,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Or => {
                             gld.mk_or(
@@ -266,7 +325,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Nor => {
                             gld.mk_nor(
@@ -295,7 +354,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Xor => {
                             gld.mk_xor(
@@ -324,7 +383,7 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Xnor => {
                             gld.mk_xnor(
@@ -353,7 +412,255 @@ impl Netlist {
                                     }
                                 },
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::AndN => {
+                            gld.mk_and_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::NandN => {
+                            gld.mk_nand_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::OrN => {
+                            gld.mk_or_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::NorN => {
+                            gld.mk_nor_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::XorN => {
+                            gld.mk_xor_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::XnorN => {
+                            gld.mk_xnor_n(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::Mux => {
+                            gld.mk_mux(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match lhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match rhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match child_port_mapping.get(3) {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::Lut(table) => {
+                            gld.mk_lut(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                &child_port_mapping[1..],
+                                table,
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::Dff { reset, set } => {
+                            let mut controls = child_port_mapping.iter().skip(3).copied();
+                            let reset = match reset {
+                                true => match controls.next() {
+                                    Some(sig) => Some(sig),
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                false => None,
+                            };
+                            let set = match set {
+                                true => match controls.next() {
+                                    Some(sig) => Some(sig),
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                false => None,
+                            };
+                            gld.mk_dff(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match lhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match rhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                reset,
+                                set,
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::DLatch => {
+                            gld.mk_dlatch(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match lhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match rhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::TriBuf => {
+                            gld.mk_tribuf(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match lhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match rhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         // special
                         PrimitiveType::Input(expr) => {
@@ -368,7 +675,7 @@ impl Netlist {
                                 },
                                 expr,
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                         PrimitiveType::Output(expr) => {
                             gld.mk_output(
@@ -390,7 +697,43 @@ impl Netlist {
                                 },
                                 expr,
                             )
-                            .unwrap();
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::LaneInput(expr) => {
+                            gld.mk_lane_input(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                expr,
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
+                        }
+                        PrimitiveType::LaneOutput(expr) => {
+                            gld.mk_lane_output(
+                                match loc {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                match lhs {
+                                    Some(sig) => *sig,
+                                    None => {
+                                        return Result::Err(
+                                            NetlistLowerError::ChildPortNotAllocated,
+                                        );
+                                    }
+                                },
+                                expr,
+                            )
+                            .map_err(NetlistLowerError::Builder)?;
                         }
                     }
                 }
@@ -433,6 +776,10 @@ struct Module {
     name: String,
     portlist: Vec<Port>,
     wires: HashMap<Drain, Source>,
+    /// Drains connected to several sources at once, every source must be the output
+    /// of a tri-state buffer in this module. Drains listed here are looked up
+    /// before `wires`.
+    buses: HashMap<Drain, Vec<Source>>,
     cells: Vec<Box<dyn Cell>>,
 }
 
@@ -442,6 +789,7 @@ impl Module {
             name: name,
             portlist: Vec::new(),
             wires: HashMap::new(),
+            buses: HashMap::new(),
             cells: Vec::new(),
         }
     }
@@ -513,10 +861,30 @@ pub enum PrimitiveType {
     Nor,
     Xor,
     Xnor,
+    // variadic gates, every port after the output is an input
+    AndN,
+    NandN,
+    OrN,
+    NorN,
+    XorN,
+    XnorN,
+    Mux,
+    /// a lookup table holding the truth table, every port after the output is an input
+    Lut(u64),
+    /// a rising edge flip-flop, the reset and set ports follow D and CLK when present
+    Dff {
+        reset: bool,
+        set: bool,
+    },
+    DLatch,
+    TriBuf,
     // it likely is possible to do these without refrence counting but
     // this should work and it's not worth engineering that right now
     Input(Arc<dyn Fn(usize, u128) -> circuit::signal::Signal + Sync + Send>),
     Output(Arc<dyn Fn(usize, u128, circuit::signal::Signal) + Sync + Send>),
+    // lane aware I/O for circuits running several stimulus vectors at once
+    LaneInput(Arc<dyn Fn(usize, u128, usize) -> circuit::signal::Signal + Sync + Send>),
+    LaneOutput(Arc<dyn Fn(usize, u128, usize, circuit::signal::Signal) + Sync + Send>),
 }
 
 #[derive(Debug)]
@@ -525,6 +893,9 @@ enum NetlistLowerError {
     ModuleHandleDNE,
     PortNotAllocated,
     ChildPortNotAllocated,
+    /// A bus is driven by something other than a tri-state buffer.
+    BusDriverNotTriState(Source),
+    Builder(circuit::builder::BuilderError),
 }
 
 #[cfg(test)]
@@ -653,15 +1024,258 @@ mod tests {
                 // No ports needed since this is top level module
                 portlist: Vec::new(),
                 wires,
+                buses: HashMap::new(),
                 cells,
             }],
         };
 
         let mut circuit = netlist.as_circuit(ModuleHandle(0), 2).unwrap();
 
-        for idx in 0..240 {
-            circuit.tick()
+        circuit.run_for(240);
+    }
+
+    /// Records every value seen on its input, used to observe the lowered circuit.
+    #[derive(Clone, Debug)]
+    struct Probe {
+        seen: Arc<std::sync::Mutex<Vec<Signal>>>,
+    }
+    impl Cell for Probe {
+        fn clone_as_box(&self) -> Box<dyn Cell> {
+            Box::new(self.clone())
+        }
+
+        fn contents(&self) -> CellContents {
+            let seen = self.seen.clone();
+            CellContents::Primitive(PrimitiveType::Output(Arc::new(move |_, _, signal| {
+                seen.lock().unwrap().push(signal)
+            })))
         }
+
+        fn interface(&self) -> CellInterface {
+            CellInterface::Builtin(Box::new([
+                Port {
+                    name: "state".to_string(),
+                    port_type: PortType::Output,
+                    local_location: Address(CellHandle(0), PortHandle(0)),
+                },
+                Port {
+                    name: "watch".to_string(),
+                    port_type: PortType::Input,
+                    local_location: Address(CellHandle(0), PortHandle(1)),
+                },
+            ]))
+        }
+    }
+
+    #[test]
+    /// two tri-state buffers taking turns driving a shared wire
+    fn tri_state_bus_lower_and_sim() {
+        use cell_types::*;
+
+        let waveform = |waveform: Vec<Signal>| -> Box<dyn Cell> {
+            Box::new(Waveform {
+                setup_time: 0,
+                waveform,
+            })
+        };
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let cells: Vec<Box<dyn Cell>> = vec![
+            // cell 00 and 01 data
+            waveform(vec![Signal::True]),
+            waveform(vec![Signal::False]),
+            // cell 02 and 03 enables, never on at the same time
+            waveform(vec![
+                Signal::True,
+                Signal::True,
+                Signal::False,
+                Signal::False,
+            ]),
+            waveform(vec![
+                Signal::False,
+                Signal::False,
+                Signal::True,
+                Signal::True,
+            ]),
+            // cell 04 and 05
+            Box::new(TriBuf {}),
+            Box::new(TriBuf {}),
+            // cell 06
+            Box::new(Probe { seen: seen.clone() }),
+        ];
+
+        let mut wires = HashMap::new();
+        for (buffer, data, enable) in [(4, 0, 2), (5, 1, 3)] {
+            wires.insert(
+                Drain(Address(CellHandle(buffer), PortHandle(1))),
+                Source(Address(CellHandle(data), PortHandle(0))),
+            );
+            wires.insert(
+                Drain(Address(CellHandle(buffer), PortHandle(2))),
+                Source(Address(CellHandle(enable), PortHandle(0))),
+            );
+        }
+        let mut buses = HashMap::new();
+        buses.insert(
+            Drain(Address(CellHandle(6), PortHandle(1))),
+            vec![
+                Source(Address(CellHandle(4), PortHandle(0))),
+                Source(Address(CellHandle(5), PortHandle(0))),
+            ],
+        );
+
+        let mut netlist = Netlist {
+            modules: vec![Module {
+                name: "Bus".to_string(),
+                portlist: Vec::new(),
+                wires,
+                buses,
+                cells,
+            }],
+        };
+
+        let mut circuit = netlist.as_circuit(ModuleHandle(0), 4).unwrap();
+        circuit.run_for(64);
+        let seen = seen.lock().unwrap();
+        assert!(seen.contains(&Signal::True));
+        assert!(seen.contains(&Signal::False));
+        assert!(!seen[8..].contains(&Signal::Undefined));
+
+        // anything other than a tri-state buffer can't share a wire
+        netlist.modules[0].cells[5] = Box::new(Inverter {});
+        assert!(matches!(
+            netlist.as_circuit(ModuleHandle(0), 4),
+            Err(NetlistLowerError::BusDriverNotTriState(Source(Address(
+                CellHandle(5),
+                PortHandle(0)
+            ))))
+        ));
+    }
+
+    #[test]
+    /// a wide and gate feeding the select of a mux
+    fn variadic_gate_and_mux_lower_and_sim() {
+        use cell_types::*;
+
+        let constant = |signal: Signal| -> Box<dyn Cell> {
+            Box::new(Waveform {
+                setup_time: 0,
+                waveform: vec![signal],
+            })
+        };
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let cells: Vec<Box<dyn Cell>> = vec![
+            // cell 00 to 03 and inputs
+            constant(Signal::True),
+            constant(Signal::True),
+            constant(Signal::True),
+            constant(Signal::True),
+            // cell 04 and 05 mux data
+            constant(Signal::False),
+            constant(Signal::True),
+            // cell 06
+            Box::new(AndNGate { inputs: 4 }),
+            // cell 07
+            Box::new(Mux {}),
+            // cell 08
+            Box::new(Probe { seen: seen.clone() }),
+        ];
+
+        let mut wires = HashMap::new();
+        for input in 0..4 {
+            wires.insert(
+                Drain(Address(CellHandle(6), PortHandle(input + 1))),
+                Source(Address(CellHandle(input), PortHandle(0))),
+            );
+        }
+        for (port, source) in [(1, 6), (2, 4), (3, 5)] {
+            wires.insert(
+                Drain(Address(CellHandle(7), PortHandle(port))),
+                Source(Address(CellHandle(source), PortHandle(0))),
+            );
+        }
+        wires.insert(
+            Drain(Address(CellHandle(8), PortHandle(1))),
+            Source(Address(CellHandle(7), PortHandle(0))),
+        );
+
+        let netlist = Netlist {
+            modules: vec![Module {
+                name: "Wide".to_string(),
+                portlist: Vec::new(),
+                wires,
+                buses: HashMap::new(),
+                cells,
+            }],
+        };
+
+        let mut circuit = netlist.as_circuit(ModuleHandle(0), 8).unwrap();
+        circuit.run_for(4);
+        // inputs, and, mux, then the probe sees it
+        assert_eq!(seen.lock().unwrap()[3], Signal::True);
+    }
+
+    /// An inverter whose output port is declared as an input, so lowering hands it
+    /// a signal some other cell already drives.
+    #[derive(Clone, Copy, Debug)]
+    struct MisdeclaredInverter {}
+    impl Cell for MisdeclaredInverter {
+        fn clone_as_box(&self) -> Box<dyn Cell> {
+            Box::new(*self)
+        }
+
+        fn contents(&self) -> CellContents {
+            CellContents::Primitive(PrimitiveType::Not)
+        }
+
+        fn interface(&self) -> CellInterface {
+            CellInterface::Builtin(Box::new([
+                Port {
+                    name: "~A".to_string(),
+                    port_type: PortType::Input,
+                    local_location: Address(CellHandle(0), PortHandle(0)),
+                },
+                Port {
+                    name: "A".to_string(),
+                    port_type: PortType::Input,
+                    local_location: Address(CellHandle(0), PortHandle(1)),
+                },
+            ]))
+        }
+    }
+
+    #[test]
+    fn builder_errors_are_returned() {
+        use cell_types::*;
+
+        let cells: Vec<Box<dyn Cell>> = vec![
+            Box::new(Waveform {
+                setup_time: 0,
+                waveform: vec![Signal::True],
+            }),
+            Box::new(MisdeclaredInverter {}),
+        ];
+        // the inverter's output is wired to the waveform, defining it twice
+        let mut wires = HashMap::new();
+        wires.insert(
+            Drain(Address(CellHandle(1), PortHandle(0))),
+            Source(Address(CellHandle(0), PortHandle(0))),
+        );
+
+        let netlist = Netlist {
+            modules: vec![Module {
+                name: "Misdeclared".to_string(),
+                portlist: Vec::new(),
+                wires,
+                buses: HashMap::new(),
+                cells,
+            }],
+        };
+        assert!(matches!(
+            netlist.as_circuit(ModuleHandle(0), 1),
+            Err(NetlistLowerError::Builder(
+                circuit::builder::BuilderError::DoubleDefinition(_)
+            ))
+        ));
     }
 
     #[test]